@@ -0,0 +1,162 @@
+//! Precomputed attack tables.
+//!
+//! Knights, kings and pawns look their attacks up directly. Sliding pieces use the classical
+//! ray approach: each square has a precomputed ray in every direction, and the ray is cut off
+//! behind the first blocker found with a bit scan, so there's no need for magic numbers or PEXT.
+
+use crate::{Bitboard, PieceColor, Square};
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+// Directions are indexed so that the first four move towards higher squares, where the closest
+// blocker is the lowest set bit, and the last four towards lower squares.
+const NORTH: usize = 0;
+const EAST: usize = 1;
+const NORTH_EAST: usize = 2;
+const NORTH_WEST: usize = 3;
+const SOUTH: usize = 4;
+const WEST: usize = 5;
+const SOUTH_WEST: usize = 6;
+const SOUTH_EAST: usize = 7;
+const DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+];
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(&KNIGHT_OFFSETS);
+const KING_ATTACKS: [u64; 64] = step_table(&KING_OFFSETS);
+const WHITE_PAWN_ATTACKS: [u64; 64] = step_table(&[(1, 1), (1, -1)]);
+const BLACK_PAWN_ATTACKS: [u64; 64] = step_table(&[(-1, 1), (-1, -1)]);
+const RAYS: [[u64; 64]; 8] = ray_tables();
+
+/// Returns the squares reached by taking a single step by any of the offsets from each square.
+const fn step_table(offsets: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let rank = (square / 8) as i8;
+        let file = (square % 8) as i8;
+        let mut i = 0;
+        while i < offsets.len() {
+            let to_rank = rank + offsets[i].0;
+            let to_file = file + offsets[i].1;
+            if to_rank >= 0 && to_rank < 8 && to_file >= 0 && to_file < 8 {
+                table[square] |= 1 << (to_rank * 8 + to_file);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Returns, for every direction and square, all squares from there to the edge of the board.
+const fn ray_tables() -> [[u64; 64]; 8] {
+    let mut tables = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let mut rank = (square / 8) as i8 + DIRECTIONS[direction].0;
+            let mut file = (square % 8) as i8 + DIRECTIONS[direction].1;
+            while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+                tables[direction][square] |= 1 << (rank * 8 + file);
+                rank += DIRECTIONS[direction].0;
+                file += DIRECTIONS[direction].1;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    tables
+}
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[square.index()])
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    Bitboard(KING_ATTACKS[square.index()])
+}
+
+/// The squares a pawn of the given color standing on the square attacks.
+pub fn pawn_attacks(color: PieceColor, square: Square) -> Bitboard {
+    match color {
+        PieceColor::White => Bitboard(WHITE_PAWN_ATTACKS[square.index()]),
+        PieceColor::Black => Bitboard(BLACK_PAWN_ATTACKS[square.index()]),
+    }
+}
+
+/// The ray from the square in the direction, up to and including the first occupied square.
+fn ray_attacks(direction: usize, square: Square, occupied: Bitboard) -> u64 {
+    let ray = RAYS[direction][square.index()];
+    let blockers = ray & occupied.0;
+    if blockers == 0 {
+        return ray;
+    }
+
+    let blocker = if direction < SOUTH {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray & !RAYS[direction][blocker as usize]
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    Bitboard(
+        ray_attacks(NORTH, square, occupied)
+            | ray_attacks(EAST, square, occupied)
+            | ray_attacks(SOUTH, square, occupied)
+            | ray_attacks(WEST, square, occupied),
+    )
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    Bitboard(
+        ray_attacks(NORTH_EAST, square, occupied)
+            | ray_attacks(NORTH_WEST, square, occupied)
+            | ray_attacks(SOUTH_EAST, square, occupied)
+            | ray_attacks(SOUTH_WEST, square, occupied),
+    )
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+/// The squares strictly between two squares on the same rank, file or diagonal, or nothing if
+/// they don't share one.
+pub fn between(a: Square, b: Square) -> Bitboard {
+    for rays in &RAYS {
+        if rays[a.index()] & (1 << b.index()) != 0 {
+            return Bitboard(rays[a.index()] & !rays[b.index()]) & !Bitboard::from_square(b);
+        }
+    }
+    Bitboard::EMPTY
+}
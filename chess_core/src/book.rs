@@ -0,0 +1,165 @@
+//! Polyglot opening books: `.bin` files of 16 byte entries, sorted by the position's Zobrist key,
+//! each giving a move for that position and how often to play it.
+
+use std::{cmp::Reverse, error::Error, fmt};
+
+use crate::{legal_moves, Move, PieceType, Position, Square};
+
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    key: u64,
+    /// The move packed into bits: to file, to rank, from file, from rank and promotion, three
+    /// bits each from the lowest.
+    mv: u16,
+    weight: u16,
+}
+
+/// A move the book has for a position, with how often it should be played compared to the
+/// position's other book moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookMove {
+    pub mv: Move,
+    pub weight: u32,
+}
+
+/// The entries of one or more books, looked up by position.
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: Vec<Entry>,
+}
+
+/// A file that can't be a Polyglot book because it doesn't divide into whole entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookError {
+    pub len: usize,
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "not a Polyglot book, {} bytes isn't a whole number of {} byte entries",
+            self.len, ENTRY_SIZE
+        )
+    }
+}
+
+impl Error for BookError {}
+
+impl Book {
+    /// Reads the contents of a `.bin` file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        let chunks = bytes.chunks_exact(ENTRY_SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(BookError { len: bytes.len() });
+        }
+
+        let mut entries: Vec<Entry> = chunks
+            .map(|entry| {
+                // Big endian, with four bytes at the end for learning that aren't used here
+                let mut key = [0; 8];
+                key.copy_from_slice(&entry[..8]);
+                Entry {
+                    key: u64::from_be_bytes(key),
+                    mv: u16::from_be_bytes([entry[8], entry[9]]),
+                    weight: u16::from_be_bytes([entry[10], entry[11]]),
+                }
+            })
+            .collect();
+        // Books should already be sorted, but lookups go wrong if one isn't
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Book { entries })
+    }
+
+    /// Adds another book's entries, so moves are looked up in both.
+    pub fn merge(&mut self, other: Book) {
+        self.entries.extend(other.entries);
+        self.entries.sort_by_key(|entry| entry.key);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The book moves for the position, most played first. A move in more than one book gets
+    /// the weights added together, and entries that aren't legal moves are left out.
+    pub fn moves(&self, position: &Position) -> Vec<BookMove> {
+        let key = position.zobrist_key();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal = legal_moves(position);
+
+        let mut moves: Vec<BookMove> = Vec::new();
+        for entry in self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+        {
+            let mv = decode_move(position, entry.mv);
+            if !legal.contains(&mv) {
+                continue;
+            }
+            match moves.iter_mut().find(|book_move| book_move.mv == mv) {
+                Some(book_move) => book_move.weight += u32::from(entry.weight),
+                None => moves.push(BookMove {
+                    mv,
+                    weight: entry.weight.into(),
+                }),
+            }
+        }
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    /// Picks one of the position's book moves at random, in proportion to their weights, using
+    /// `random` as the roll. `None` once the game is out of book.
+    pub fn pick(&self, position: &Position, random: u64) -> Option<Move> {
+        let moves = self.moves(position);
+        let total: u64 = moves
+            .iter()
+            .map(|book_move| u64::from(book_move.weight))
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = random % total;
+        for book_move in moves {
+            let weight = u64::from(book_move.weight);
+            if roll < weight {
+                return Some(book_move.mv);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+fn decode_move(position: &Position, mv: u16) -> Move {
+    let square = |shift: u16| {
+        let file = (mv >> shift) & 7;
+        let rank = (mv >> (shift + 3)) & 7;
+        Square::from_rank_file(rank as u8, file as u8)
+    };
+    let from = square(6);
+    let mut to = square(0);
+
+    // Castling is written as the king taking its own rook
+    let is_king =
+        matches!(position.piece_at(from), Some(piece) if piece.piece_type == PieceType::King);
+    if is_king && from.file() == 4 && to.rank() == from.rank() {
+        match to.file() {
+            7 => to = Square::from_rank_file(from.rank(), 6),
+            0 => to = Square::from_rank_file(from.rank(), 2),
+            _ => {}
+        }
+    }
+
+    match (mv >> 12) & 7 {
+        1 => Move::with_promotion(from, to, PieceType::Knight),
+        2 => Move::with_promotion(from, to, PieceType::Bishop),
+        3 => Move::with_promotion(from, to, PieceType::Rook),
+        4 => Move::with_promotion(from, to, PieceType::Queen),
+        _ => Move::new(from, to),
+    }
+}
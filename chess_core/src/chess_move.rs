@@ -0,0 +1,44 @@
+use std::fmt;
+
+use crate::{PieceType, Square};
+
+/// A move from one square to another. Castling is the king moving two squares, and the position
+/// works out en passant captures on its own, so only promotions need extra information.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Self {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceType) -> Self {
+        Move {
+            from,
+            to,
+            promotion: Some(promotion),
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    /// Formats the move in long algebraic notation as used by UCI, e.g. `e2e4` or `e7e8q`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(PieceType::Queen) => write!(f, "q"),
+            Some(PieceType::Rook) => write!(f, "r"),
+            Some(PieceType::Bishop) => write!(f, "b"),
+            Some(PieceType::Knight) => write!(f, "n"),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Chess clocks for the usual time controls: sudden death, Fischer increment, Bronstein delay
+//! and classical controls with several periods.
+
+use std::{error::Error, fmt, time::Duration};
+
+use crate::PieceColor;
+
+/// Time added back after each move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bonus {
+    None,
+    /// The same time added after every move.
+    Fischer(Duration),
+    /// The time the move took is given back, up to the delay.
+    Bronstein(Duration),
+}
+
+/// A stretch of the game with its own time. Periods with a move count add their time to the
+/// clock once that many moves have been played in them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Period {
+    /// Moves to play in this period, `None` for the rest of the game.
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+/// How much time each player gets. The last period repeats if it has a move count, as in
+/// `40/120` for two hours every 40 moves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub periods: Vec<Period>,
+    pub bonus: Bonus,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimeControlError {
+    InvalidPeriod(String),
+    InvalidBonus(String),
+    /// Only the last period can leave out the move count.
+    UnlimitedPeriodNotLast,
+}
+
+impl fmt::Display for TimeControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeControlError::InvalidPeriod(period) => write!(f, "invalid period `{}`", period),
+            TimeControlError::InvalidBonus(bonus) => write!(f, "invalid increment `{}`", bonus),
+            TimeControlError::UnlimitedPeriodNotLast => {
+                write!(f, "only the last period can be for the rest of the game")
+            }
+        }
+    }
+}
+
+impl Error for TimeControlError {}
+
+impl TimeControl {
+    /// Parses periods of `[moves/]minutes` separated by commas, with an increment in seconds
+    /// after a `+` or a Bronstein delay after a `d` at the end. For example `5+3`, `15d10` or
+    /// `40/90,30+30` for 90 minutes for 40 moves then 30 for the rest of the game, with 30
+    /// seconds added each move. Without the second period, `40/90+30` gives 90 more minutes
+    /// every 40 moves.
+    pub fn parse(text: &str) -> Result<Self, TimeControlError> {
+        let (periods_text, bonus) = if let Some(at) = text.find('+') {
+            let seconds = parse_seconds(&text[at + 1..])?;
+            (&text[..at], Bonus::Fischer(seconds))
+        } else if let Some(at) = text.find('d') {
+            let seconds = parse_seconds(&text[at + 1..])?;
+            (&text[..at], Bonus::Bronstein(seconds))
+        } else {
+            (text, Bonus::None)
+        };
+
+        let mut periods = Vec::new();
+        for period_text in periods_text.split(',') {
+            let invalid = || TimeControlError::InvalidPeriod(period_text.to_string());
+            let (moves, minutes) = match period_text.find('/') {
+                Some(at) => (
+                    Some(period_text[..at].parse().map_err(|_| invalid())?),
+                    &period_text[at + 1..],
+                ),
+                None => (None, period_text),
+            };
+            let minutes: f64 = minutes.parse().map_err(|_| invalid())?;
+            if moves == Some(0) || minutes <= 0.0 {
+                return Err(invalid());
+            }
+            // Also rules out NaN, infinity and times too long for a `Duration`
+            let time = Duration::try_from_secs_f64(minutes * 60.0).map_err(|_| invalid())?;
+            periods.push(Period { moves, time });
+        }
+        let last = periods.len() - 1;
+        if periods[..last].iter().any(|period| period.moves.is_none()) {
+            return Err(TimeControlError::UnlimitedPeriodNotLast);
+        }
+
+        Ok(TimeControl { periods, bonus })
+    }
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, TimeControlError> {
+    text.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| TimeControlError::InvalidBonus(text.to_string()))
+}
+
+/// Both players' time. It doesn't keep time itself: the caller says how much time passed for
+/// whoever is thinking with `tick`, and when they finished their move with `finish_move`.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    /// Indexed by `PieceColor::index`, like everything else per side.
+    remaining: [Duration; 2],
+    /// Which period each side is in, and how many moves they've played in it.
+    period: [usize; 2],
+    moves_in_period: [u32; 2],
+    /// Time spent on the move being thought about, for the Bronstein delay.
+    move_time: Duration,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        let time = control.periods[0].time;
+        Clock {
+            control,
+            remaining: [time; 2],
+            period: [0; 2],
+            moves_in_period: [0; 2],
+            move_time: Duration::default(),
+        }
+    }
+
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        self.remaining[color.index()]
+    }
+
+    /// Whether the side has run out of time.
+    pub fn is_flagged(&self, color: PieceColor) -> bool {
+        self.remaining(color) == Duration::default()
+    }
+
+    /// Takes time off the clock of the side that is thinking.
+    pub fn tick(&mut self, color: PieceColor, elapsed: Duration) {
+        let remaining = &mut self.remaining[color.index()];
+        *remaining = remaining.checked_sub(elapsed).unwrap_or_default();
+        self.move_time = self.move_time.saturating_add(elapsed);
+    }
+
+    /// Adds the bonus for the move just played, and the next period's time once a period's
+    /// moves are done.
+    pub fn finish_move(&mut self, color: PieceColor) {
+        let side = color.index();
+        if self.is_flagged(color) {
+            return;
+        }
+
+        let bonus = match self.control.bonus {
+            Bonus::None => Duration::default(),
+            Bonus::Fischer(increment) => increment,
+            Bonus::Bronstein(delay) => delay.min(self.move_time),
+        };
+        self.remaining[side] = self.remaining[side].saturating_add(bonus);
+        self.move_time = Duration::default();
+
+        self.moves_in_period[side] += 1;
+        let period = self.control.periods[self.period[side]];
+        if Some(self.moves_in_period[side]) == period.moves {
+            // The last period starts over when its moves are done
+            self.period[side] = (self.period[side] + 1).min(self.control.periods.len() - 1);
+            self.moves_in_period[side] = 0;
+            let time = self.control.periods[self.period[side]].time;
+            self.remaining[side] = self.remaining[side].saturating_add(time);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Static evaluation from material and piece-square tables, in centipawns.
+
+use crate::{PieceColor, PieceType, Position, Square};
+
+/// What each piece type is worth, indexed by `PieceType::index`.
+pub const PIECE_VALUES: [i32; 6] = [0, 900, 330, 320, 500, 100];
+
+// Piece-square tables from White's point of view with a8 first, so they read like a board.
+// Values from Tomasz Michniewski's "Simplified Evaluation Function".
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+/// The king should stay tucked away while there are pieces around...
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+/// ...and come to the centre in the endgame.
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// Below this much material for the opponent, not counting pawns and kings, a king is in its
+/// endgame.
+const ENDGAME_MATERIAL: i32 = 1300;
+
+fn table_value(piece_type: PieceType, color: PieceColor, square: Square, endgame: bool) -> i32 {
+    // The tables start at a8, so White's squares are flipped and Black's are mirrored
+    let index = match color {
+        PieceColor::White => (7 - square.rank() as usize) * 8 + square.file() as usize,
+        PieceColor::Black => square.index(),
+    };
+    let table = match piece_type {
+        PieceType::King if endgame => &KING_ENDGAME_TABLE,
+        PieceType::King => &KING_MIDDLEGAME_TABLE,
+        PieceType::Queen => &QUEEN_TABLE,
+        PieceType::Bishop => &BISHOP_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Rook => &ROOK_TABLE,
+        PieceType::Pawn => &PAWN_TABLE,
+    };
+    table[index]
+}
+
+/// Material not counting pawns and the king.
+fn piece_material(position: &Position, color: PieceColor) -> i32 {
+    [
+        PieceType::Queen,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Knight,
+    ]
+    .iter()
+    .map(|piece_type| {
+        position.piece_bitboard(color, *piece_type).count() as i32
+            * PIECE_VALUES[piece_type.index()]
+    })
+    .sum()
+}
+
+/// The position's score for the side to move, positive when they are better.
+pub fn evaluate(position: &Position) -> i32 {
+    let endgame = [
+        piece_material(position, PieceColor::Black) < ENDGAME_MATERIAL,
+        piece_material(position, PieceColor::White) < ENDGAME_MATERIAL,
+    ];
+
+    let mut score = 0;
+    for (square, piece) in position.pieces() {
+        let value = PIECE_VALUES[piece.piece_type.index()]
+            + table_value(
+                piece.piece_type,
+                piece.color,
+                square,
+                endgame[piece.color.index()],
+            );
+        match piece.color {
+            PieceColor::White => score += value,
+            PieceColor::Black => score -= value,
+        }
+    }
+
+    match position.side_to_move() {
+        PieceColor::White => score,
+        PieceColor::Black => -score,
+    }
+}
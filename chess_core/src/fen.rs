@@ -0,0 +1,241 @@
+//! Forsyth–Edwards Notation, e.g.
+//! `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1`.
+
+use std::{error::Error, fmt};
+
+use crate::{CastlingRights, Piece, PieceColor, PieceType, Position, Square};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// The board, side to move, castling and en passant fields are required, the move counters
+    /// are optional.
+    WrongFieldCount(usize),
+    InvalidBoard(String),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidMoveCounter(String),
+    /// Each side needs exactly one king.
+    InvalidKings,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 to 6 fields but found {}", count)
+            }
+            FenError::InvalidBoard(board) => write!(f, "invalid board `{}`", board),
+            FenError::InvalidSideToMove(side) => write!(f, "invalid side to move `{}`", side),
+            FenError::InvalidCastling(castling) => {
+                write!(f, "invalid castling rights `{}`", castling)
+            }
+            FenError::InvalidEnPassant(square) => {
+                write!(f, "invalid en passant square `{}`", square)
+            }
+            FenError::InvalidMoveCounter(counter) => {
+                write!(f, "invalid move counter `{}`", counter)
+            }
+            FenError::InvalidKings => write!(f, "each side needs exactly one king"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// The letter for a piece, uppercase for White and lowercase for Black.
+pub(crate) fn piece_char(piece: Piece) -> char {
+    let c = match piece.piece_type {
+        PieceType::King => 'k',
+        PieceType::Queen => 'q',
+        PieceType::Bishop => 'b',
+        PieceType::Knight => 'n',
+        PieceType::Rook => 'r',
+        PieceType::Pawn => 'p',
+    };
+    match piece.color {
+        PieceColor::White => c.to_ascii_uppercase(),
+        PieceColor::Black => c,
+    }
+}
+
+pub(crate) fn piece_from_char(c: char) -> Option<Piece> {
+    let piece_type = match c.to_ascii_lowercase() {
+        'k' => PieceType::King,
+        'q' => PieceType::Queen,
+        'b' => PieceType::Bishop,
+        'n' => PieceType::Knight,
+        'r' => PieceType::Rook,
+        'p' => PieceType::Pawn,
+        _ => return None,
+    };
+    let color = if c.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    Some(Piece::new(color, piece_type))
+}
+
+impl Position {
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut position = Position::empty();
+
+        // Ranks go from 8 down to 1, files from a to h
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidBoard(fields[0].to_string()));
+        }
+        for (idx, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - idx as u8;
+            let mut file = 0;
+            for c in rank_text.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    if empty == 0 {
+                        return Err(FenError::InvalidBoard(fields[0].to_string()));
+                    }
+                    file += empty as u8;
+                } else {
+                    let piece = piece_from_char(c)
+                        .filter(|_| file < 8)
+                        .ok_or_else(|| FenError::InvalidBoard(fields[0].to_string()))?;
+                    position.put_piece(Square::from_rank_file(rank, file), piece);
+                    file += 1;
+                }
+            }
+            if file != 8 {
+                return Err(FenError::InvalidBoard(fields[0].to_string()));
+            }
+        }
+        for color in &PieceColor::ALL {
+            if position.piece_bitboard(*color, PieceType::King).count() != 1 {
+                return Err(FenError::InvalidKings);
+            }
+        }
+
+        position.side_to_move = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            side => return Err(FenError::InvalidSideToMove(side.to_string())),
+        };
+
+        let mut castling_rights = CastlingRights::none();
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => castling_rights.white_kingside = true,
+                    'Q' => castling_rights.white_queenside = true,
+                    'k' => castling_rights.black_kingside = true,
+                    'q' => castling_rights.black_queenside = true,
+                    _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+                }
+            }
+        }
+        position.castling_rights = castling_rights;
+
+        // The square a pawn of the side that just moved skipped over, with that pawn in front
+        position.en_passant = match fields[3] {
+            "-" => None,
+            text => {
+                let (rank, pawn_rank) = match position.side_to_move {
+                    PieceColor::White => (5, 4),
+                    PieceColor::Black => (2, 3),
+                };
+                let pawn = Piece::new(position.side_to_move.opposite(), PieceType::Pawn);
+                match text.parse::<Square>() {
+                    Ok(square)
+                        if square.rank() == rank
+                            && position
+                                .piece_at(Square::from_rank_file(pawn_rank, square.file()))
+                                == Some(pawn) =>
+                    {
+                        Some(square)
+                    }
+                    _ => return Err(FenError::InvalidEnPassant(text.to_string())),
+                }
+            }
+        };
+
+        if let Some(halfmove_clock) = fields.get(4) {
+            position.halfmove_clock = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidMoveCounter(halfmove_clock.to_string()))?;
+        }
+        if let Some(fullmove_number) = fields.get(5) {
+            position.fullmove_number = fullmove_number
+                .parse()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| FenError::InvalidMoveCounter(fullmove_number.to_string()))?;
+        }
+
+        position.refresh_key();
+        Ok(position)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::from_rank_file(rank, file)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_char(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.side_to_move {
+            PieceColor::White => " w ",
+            PieceColor::Black => " b ",
+        });
+
+        let castling_rights = self.castling_rights;
+        let mut castling = String::new();
+        if castling_rights.white_kingside {
+            castling.push('K');
+        }
+        if castling_rights.white_queenside {
+            castling.push('Q');
+        }
+        if castling_rights.black_kingside {
+            castling.push('k');
+        }
+        if castling_rights.black_queenside {
+            castling.push('q');
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        fen.push_str(&castling);
+
+        match self.en_passant {
+            Some(square) => fen.push_str(&format!(" {}", square)),
+            None => fen.push_str(" -"),
+        }
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{GameStatus, Move, PieceColor, Position, Undo};
+
+/// How a game ended, or that it hasn't yet.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Ongoing,
+}
+
+impl GameResult {
+    /// The result as written in PGN, e.g. `1-0`.
+    pub fn to_pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        }
+    }
+
+    pub fn win_for(color: PieceColor) -> Self {
+        match color {
+            PieceColor::White => GameResult::WhiteWins,
+            PieceColor::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Resignation,
+    /// Claimed after 50 moves by each side without a capture or pawn move.
+    FiftyMoveRule,
+    /// Automatic after 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// Claimed when the same position comes up for the third time.
+    ThreefoldRepetition,
+    /// Automatic when the same position comes up for the fifth time.
+    FivefoldRepetition,
+    /// Neither side has enough left to checkmate.
+    InsufficientMaterial,
+    /// Ran out of time while the opponent could still mate.
+    Timeout,
+    /// Ran out of time, but the opponent had nothing left to mate with.
+    TimeoutVsInsufficientMaterial,
+}
+
+impl Termination {
+    pub fn description(self) -> &'static str {
+        match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Resignation => "resignation",
+            Termination::FiftyMoveRule => "fifty-move rule",
+            Termination::SeventyFiveMoveRule => "seventy-five-move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Timeout => "timeout",
+            Termination::TimeoutVsInsufficientMaterial => "timeout vs insufficient material",
+        }
+    }
+}
+
+/// The result of a finished game and why it ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+/// A game from some starting position, with every move played so far.
+#[derive(Clone, Debug)]
+pub struct Game {
+    starting_position: Position,
+    position: Position,
+    moves: Vec<Move>,
+    /// What it takes to undo each move in `moves`.
+    undos: Vec<Undo>,
+    /// `Position::zobrist_key` of the starting position and of the position after each move.
+    keys: Vec<u64>,
+    /// Moves taken back, most recent last, until a different move is played.
+    redo_moves: Vec<Move>,
+    /// How the game ended when it wasn't on the board, e.g. by resignation.
+    ended: Option<Outcome>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Position::default())
+    }
+}
+
+impl Game {
+    pub fn new(starting_position: Position) -> Self {
+        Game {
+            position: starting_position.clone(),
+            keys: vec![starting_position.zobrist_key()],
+            starting_position,
+            moves: Vec::new(),
+            undos: Vec::new(),
+            redo_moves: Vec::new(),
+            ended: None,
+        }
+    }
+
+    pub fn starting_position(&self) -> &Position {
+        &self.starting_position
+    }
+
+    /// The position after every move so far.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// `Position::zobrist_key` of the starting position and of the position after each move.
+    pub fn keys(&self) -> &[u64] {
+        &self.keys
+    }
+
+    /// Plays a move, which has to be legal in the current position.
+    pub fn play(&mut self, mv: Move) {
+        debug_assert!(self.position.is_legal(mv), "illegal move {}", mv);
+        self.push_move(mv);
+        // Playing the move that was taken back keeps the rest of the moves to redo
+        if self.redo_moves.last() == Some(&mv) {
+            self.redo_moves.pop();
+        } else {
+            self.redo_moves.clear();
+        }
+    }
+
+    fn push_move(&mut self, mv: Move) {
+        self.undos.push(self.position.make_move(mv));
+        self.moves.push(mv);
+        self.keys.push(self.position.zobrist_key());
+    }
+
+    /// Takes back the last move, returning it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.ended = None;
+        let undo = self.undos.pop().expect("an undo for every move");
+        self.position.unmake_move(mv, undo);
+        self.keys.pop();
+        self.redo_moves.push(mv);
+        Some(mv)
+    }
+
+    /// Plays the last move taken back again, returning it.
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = self.redo_moves.pop()?;
+        self.push_move(mv);
+        Some(mv)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.moves.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_moves.is_empty()
+    }
+
+    /// Ends the game without it being decided on the board, e.g. when a player resigns.
+    pub fn end(&mut self, result: GameResult, termination: Termination) {
+        self.ended = Some(Outcome {
+            result,
+            termination,
+        });
+    }
+
+    pub fn resign(&mut self, color: PieceColor) {
+        self.end(
+            GameResult::win_for(color.opposite()),
+            Termination::Resignation,
+        );
+    }
+
+    /// Ends the game when the given side runs out of time. It's only lost if the opponent could
+    /// still have mated.
+    pub fn flag(&mut self, color: PieceColor) {
+        if self.position.has_insufficient_material(color.opposite()) {
+            self.end(GameResult::Draw, Termination::TimeoutVsInsufficientMaterial);
+        } else {
+            self.end(GameResult::win_for(color.opposite()), Termination::Timeout);
+        }
+    }
+
+    /// How many times the current position has come up, counting this time.
+    pub fn repetitions(&self) -> usize {
+        let key = self.keys[self.keys.len() - 1];
+        // A capture or pawn move can't be taken back, so nothing before one can repeat
+        let reversible = self.position.halfmove_clock() as usize + 1;
+        self.keys
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|earlier| **earlier == key)
+            .count()
+    }
+
+    /// A draw the player to move may claim but doesn't have to.
+    pub fn claimable_draw(&self) -> Option<Termination> {
+        if self.is_over() {
+            None
+        } else if self.repetitions() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.position.halfmove_clock() >= 100 {
+            Some(Termination::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Ends the game in a draw if one can be claimed, returning whether it was.
+    pub fn claim_draw(&mut self) -> bool {
+        match self.claimable_draw() {
+            Some(termination) => {
+                self.end(GameResult::Draw, termination);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// How the game ended, or `None` while it's still going. Besides checkmate and stalemate,
+    /// the game is drawn straight away on the fifth repetition, after 75 moves each without a
+    /// capture or pawn move, or once neither side can checkmate.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.ended.is_some() {
+            return self.ended;
+        }
+        let (result, termination) = match self.position.status() {
+            GameStatus::Checkmate => (
+                GameResult::win_for(self.position.side_to_move().opposite()),
+                Termination::Checkmate,
+            ),
+            GameStatus::Stalemate => (GameResult::Draw, Termination::Stalemate),
+            GameStatus::InProgress => {
+                let termination = if PieceColor::ALL
+                    .iter()
+                    .all(|color| self.position.has_insufficient_material(*color))
+                {
+                    Termination::InsufficientMaterial
+                } else if self.repetitions() >= 5 {
+                    Termination::FivefoldRepetition
+                } else if self.position.halfmove_clock() >= 150 {
+                    Termination::SeventyFiveMoveRule
+                } else {
+                    return None;
+                };
+                (GameResult::Draw, termination)
+            }
+        };
+        Some(Outcome {
+            result,
+            termination,
+        })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome().is_some()
+    }
+
+    pub fn result(&self) -> GameResult {
+        match self.outcome() {
+            Some(outcome) => outcome.result,
+            None => GameResult::Ongoing,
+        }
+    }
+}
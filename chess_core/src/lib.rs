@@ -0,0 +1,31 @@
+//! Chess rules without any rendering: positions, moves, legal move generation and make/unmake.
+//!
+//! Squares are numbered from a1 = 0 to h8 = 63, rank by rank.
+
+pub mod attacks;
+mod bitboard;
+pub use bitboard::Bitboard;
+pub mod book;
+pub mod clock;
+mod piece;
+pub use piece::{Piece, PieceColor, PieceType};
+mod square;
+pub use square::Square;
+mod chess_move;
+pub use chess_move::Move;
+pub mod eval;
+pub mod fen;
+pub use fen::FenError;
+mod game;
+pub use game::{Game, GameResult, Outcome, Termination};
+pub mod movegen;
+pub use movegen::{legal_moves, legal_moves_from};
+pub mod perft;
+mod position;
+pub use position::{CastlingRights, GameStatus, Position, Undo};
+pub mod pgn;
+pub mod san;
+pub mod search;
+pub use search::{search, search_with_progress, SearchLimits, SearchResult};
+pub mod tablebase;
+mod zobrist;
@@ -0,0 +1,120 @@
+use crate::{attacks, Bitboard, Move, PieceColor, PieceType, Position, Square};
+
+/// What a pawn can become when it reaches the last rank.
+pub const PROMOTION_TYPES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// Returns every legal move for the side to move, including castling, en passant and each
+/// possible promotion.
+pub fn legal_moves(position: &Position) -> Vec<Move> {
+    let mut moves = Vec::new();
+    for from in position.color_bitboard(position.side_to_move()) {
+        pseudo_legal_moves_from(position, from, &mut moves);
+    }
+    retain_legal(position, &mut moves);
+    moves
+}
+
+/// Returns the legal moves of the piece on `from`, which is empty if the square is empty or
+/// holds a piece of the side not to move.
+pub fn legal_moves_from(position: &Position, from: Square) -> Vec<Move> {
+    let mut moves = Vec::new();
+    if position
+        .color_bitboard(position.side_to_move())
+        .contains(from)
+    {
+        pseudo_legal_moves_from(position, from, &mut moves);
+    }
+    retain_legal(position, &mut moves);
+    moves
+}
+
+/// Drops the moves that leave the king in check, by playing each out and taking it back.
+fn retain_legal(position: &Position, moves: &mut Vec<Move>) {
+    let color = position.side_to_move();
+    let mut scratch = position.clone();
+    moves.retain(|mv| {
+        let undo = scratch.make_move(*mv);
+        let is_legal = !scratch.is_in_check(color);
+        scratch.unmake_move(*mv, undo);
+        is_legal
+    });
+}
+
+/// Adds the moves the piece on `from` can make, ignoring whether they leave the king in check.
+fn pseudo_legal_moves_from(position: &Position, from: Square, moves: &mut Vec<Move>) {
+    let piece = match position.piece_at(from) {
+        Some(piece) => piece,
+        None => return,
+    };
+    let occupied = position.occupied();
+    let not_own = !position.color_bitboard(piece.color);
+
+    let targets = match piece.piece_type {
+        PieceType::Pawn => return pawn_moves(position, from, moves),
+        PieceType::Knight => attacks::knight_attacks(from),
+        PieceType::Bishop => attacks::bishop_attacks(from, occupied),
+        PieceType::Rook => attacks::rook_attacks(from, occupied),
+        PieceType::Queen => attacks::queen_attacks(from, occupied),
+        PieceType::King => {
+            for kingside in &[true, false] {
+                if position.can_castle(*kingside) {
+                    let to_file = if *kingside { 6 } else { 2 };
+                    moves.push(Move::new(
+                        from,
+                        Square::from_rank_file(from.rank(), to_file),
+                    ));
+                }
+            }
+            attacks::king_attacks(from)
+        }
+    };
+    for to in targets & not_own {
+        moves.push(Move::new(from, to));
+    }
+}
+
+fn pawn_moves(position: &Position, from: Square, moves: &mut Vec<Move>) {
+    let color = position.side_to_move();
+    let (forward, start_rank, last_rank) = match color {
+        PieceColor::White => (1, 1, 7),
+        PieceColor::Black => (-1, 6, 0),
+    };
+    let mut push = |to: Square| {
+        if to.rank() == last_rank {
+            for promotion in &PROMOTION_TYPES {
+                moves.push(Move::with_promotion(from, to, *promotion));
+            }
+        } else {
+            moves.push(Move::new(from, to));
+        }
+    };
+
+    // Normal move, or move 2 squares from the starting rank
+    let occupied = position.occupied();
+    if let Some(to) = from.offset(forward, 0) {
+        if !occupied.contains(to) {
+            push(to);
+            if from.rank() == start_rank {
+                if let Some(to) = to.offset(forward, 0) {
+                    if !occupied.contains(to) {
+                        push(to);
+                    }
+                }
+            }
+        }
+    }
+
+    // Take piece, possibly en passant
+    let mut capturable = position.color_bitboard(color.opposite());
+    if let Some(en_passant) = position.en_passant() {
+        capturable |= Bitboard::from_square(en_passant);
+    }
+    for to in attacks::pawn_attacks(color, from) & capturable {
+        push(to);
+    }
+}
@@ -0,0 +1,44 @@
+//! Counting the positions reachable in a number of moves, to check move generation against
+//! published numbers.
+
+use crate::{Move, Position};
+
+/// Counts the leaf nodes of the legal move tree `depth` plies deep.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    let mut position = position.clone();
+    count(&mut position, depth)
+}
+
+/// The perft count below each legal move, for finding which move a wrong total comes from.
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    let mut position = position.clone();
+    position
+        .legal_moves()
+        .into_iter()
+        .map(|mv| {
+            let undo = position.make_move(mv);
+            let nodes = count(&mut position, depth.saturating_sub(1));
+            position.unmake_move(mv, undo);
+            (mv, nodes)
+        })
+        .collect()
+}
+
+fn count(position: &mut Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    // Every legal move is a leaf, no need to play them
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        let undo = position.make_move(mv);
+        nodes += count(position, depth - 1);
+        position.unmake_move(mv, undo);
+    }
+    nodes
+}
@@ -0,0 +1,318 @@
+//! Portable Game Notation, the standard format for saving whole games.
+
+use std::{error::Error, fmt};
+
+use crate::{san, Game, PieceColor, Position};
+
+/// PGN lines are kept under this many characters.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// The Seven Tag Roster every PGN game starts with, apart from the result which comes from the
+/// game itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnHeaders {
+    pub event: String,
+    pub site: String,
+    /// As `YYYY.MM.DD`, with `??` for unknown parts.
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnHeaders {
+    fn default() -> Self {
+        PgnHeaders {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "?".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// Writes the game as PGN, with SAN moves and the current result.
+pub fn write_pgn(game: &Game, headers: &PgnHeaders) -> String {
+    let result = game.result().to_pgn();
+    let mut pgn = String::new();
+    let mut push_tag = |name: &str, value: &str| {
+        pgn.push_str(&format!(
+            "[{} \"{}\"]\n",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    };
+    push_tag("Event", &headers.event);
+    push_tag("Site", &headers.site);
+    push_tag("Date", &headers.date);
+    push_tag("Round", &headers.round);
+    push_tag("White", &headers.white);
+    push_tag("Black", &headers.black);
+    push_tag("Result", result);
+
+    // Games that don't start from the usual position have to say where they start
+    if *game.starting_position() != Position::starting() {
+        push_tag("SetUp", "1");
+        push_tag("FEN", &game.starting_position().to_fen());
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut position = game.starting_position().clone();
+    for (idx, mv) in game.moves().iter().enumerate() {
+        match position.side_to_move() {
+            PieceColor::White => tokens.push(format!("{}.", position.fullmove_number())),
+            PieceColor::Black if idx == 0 => {
+                tokens.push(format!("{}...", position.fullmove_number()))
+            }
+            PieceColor::Black => {}
+        }
+        tokens.push(san::to_san(&position, *mv));
+        position.make_move(*mv);
+    }
+    tokens.push(result.to_string());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+/// A game read from a PGN file.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    /// Every tag pair in the order they appear, e.g. `("White", "Carlsen, Magnus")`.
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+    /// The result at the end of the movetext, which for games that ended by resignation or on
+    /// time can't be worked out from the moves.
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// A short name for the game like `Carlsen, Magnus - Caruana, Fabiano`.
+    pub fn title(&self) -> String {
+        format!(
+            "{} - {}",
+            self.tag("White").unwrap_or("?"),
+            self.tag("Black").unwrap_or("?")
+        )
+    }
+}
+
+/// Why a PGN file couldn't be read, pointing at the game and ply where things went wrong.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    /// Which game in the file, counting from 1.
+    pub game_number: usize,
+    pub game_title: String,
+    /// Which half-move in the game, counting from 1, if the problem is with a move.
+    pub ply: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "game {} ({})", self.game_number, self.game_title)?;
+        if let Some(ply) = self.ply {
+            write!(f, ", ply {}", ply)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for PgnError {}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(String),
+}
+
+/// Splits PGN text into tags, moves and results, dropping comments, variations, move numbers and
+/// numeric annotation glyphs.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut variation_depth: u32 = 0;
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            '{' => {
+                // Comments can span lines but don't nest
+                for c in &mut chars {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for c in &mut chars {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                line_start = true;
+            }
+            '%' if at_line_start => {
+                // Escaped line, only when the `%` is the first thing on it
+                for c in &mut chars {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                line_start = true;
+            }
+            '(' => variation_depth += 1,
+            // A stray `)` doesn't hide the moves after it
+            ')' => variation_depth = variation_depth.saturating_sub(1),
+            '[' if variation_depth == 0 => {
+                let mut tag = String::new();
+                let mut in_string = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if in_string => {
+                            if let Some(escaped) = chars.next() {
+                                tag.push(escaped);
+                            }
+                        }
+                        '"' => {
+                            in_string = !in_string;
+                            tag.push(c);
+                        }
+                        ']' if !in_string => break,
+                        _ => tag.push(c),
+                    }
+                }
+                let tag = tag.trim();
+                if let Some(space) = tag.find(char::is_whitespace) {
+                    let value = tag[space..].trim();
+                    let value = value
+                        .strip_prefix('"')
+                        .and_then(|value| value.strip_suffix('"'))
+                        .unwrap_or(value);
+                    tokens.push(Token::Tag(tag[..space].to_string(), value.to_string()));
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                // NAGs like `$1`, and `!?` when not written against the move
+                let annotation = word.starts_with('$') || word.chars().all(|c| "!?".contains(c));
+                if variation_depth > 0 || annotation {
+                    continue;
+                }
+
+                match word.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result(word)),
+                    _ => {
+                        // Move numbers like `12.` or `12...`, possibly glued to the move
+                        if !(word.starts_with(|c: char| c.is_ascii_digit()) && word.contains('.')) {
+                            tokens.push(Token::Move(word));
+                            continue;
+                        }
+                        let san = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                        if !san.is_empty() {
+                            tokens.push(Token::Move(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Reads every game in a PGN file. Games that set up their own position with a `FEN` tag start
+/// from there.
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut tags = Vec::new();
+    let mut moves = Vec::new();
+
+    for token in tokenize(text) {
+        match token {
+            Token::Tag(name, value) => {
+                // A tag after some moves means the previous game didn't end with a result
+                if !moves.is_empty() {
+                    let tags = std::mem::take(&mut tags);
+                    let moves = std::mem::take(&mut moves);
+                    games.push(build_game(games.len() + 1, tags, moves, "*".to_string())?);
+                }
+                tags.push((name, value));
+            }
+            Token::Move(san) => moves.push(san),
+            Token::Result(result) => {
+                let tags = std::mem::take(&mut tags);
+                let moves = std::mem::take(&mut moves);
+                games.push(build_game(games.len() + 1, tags, moves, result)?);
+            }
+        }
+    }
+    if !tags.is_empty() || !moves.is_empty() {
+        games.push(build_game(games.len() + 1, tags, moves, "*".to_string())?);
+    }
+    Ok(games)
+}
+
+fn build_game(
+    game_number: usize,
+    tags: Vec<(String, String)>,
+    moves: Vec<String>,
+    result: String,
+) -> Result<PgnGame, PgnError> {
+    let mut pgn_game = PgnGame {
+        tags,
+        game: Game::default(),
+        result,
+    };
+    let error = |pgn_game: &PgnGame, ply, message| PgnError {
+        game_number,
+        game_title: pgn_game.title(),
+        ply,
+        message,
+    };
+
+    if let Some(fen) = pgn_game.tag("FEN") {
+        let position = Position::from_fen(fen)
+            .map_err(|err| error(&pgn_game, None, format!("invalid FEN tag: {}", err)))?;
+        pgn_game.game = Game::new(position);
+    }
+
+    for (idx, san) in moves.iter().enumerate() {
+        let mv = san::parse_san(pgn_game.game.position(), san)
+            .map_err(|err| error(&pgn_game, Some(idx + 1), err.to_string()))?;
+        pgn_game.game.play(mv);
+    }
+    Ok(pgn_game)
+}
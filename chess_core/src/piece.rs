@@ -0,0 +1,59 @@
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub const ALL: [PieceColor; 2] = [PieceColor::White, PieceColor::Black];
+
+    /// Index into tables that have an entry per color.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceType {
+    King,
+    Queen,
+    Bishop,
+    Knight,
+    Rook,
+    Pawn,
+}
+
+impl PieceType {
+    pub const ALL: [PieceType; 6] = [
+        PieceType::King,
+        PieceType::Queen,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Rook,
+        PieceType::Pawn,
+    ];
+
+    /// Index into tables that have an entry per piece type.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Piece {
+    pub color: PieceColor,
+    pub piece_type: PieceType,
+}
+
+impl Piece {
+    pub fn new(color: PieceColor, piece_type: PieceType) -> Self {
+        Piece { color, piece_type }
+    }
+}
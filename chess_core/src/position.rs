@@ -0,0 +1,490 @@
+use crate::{attacks, movegen, zobrist, Bitboard, Move, Piece, PieceColor, PieceType, Square};
+
+/// Which sides each player can still castle to. A right is lost once the king or that rook moves,
+/// or the rook is captured.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        CastlingRights {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    pub fn has(&self, color: PieceColor, kingside: bool) -> bool {
+        match (color, kingside) {
+            (PieceColor::White, true) => self.white_kingside,
+            (PieceColor::White, false) => self.white_queenside,
+            (PieceColor::Black, true) => self.black_kingside,
+            (PieceColor::Black, false) => self.black_queenside,
+        }
+    }
+
+    /// Drops any right that depends on a king or rook standing on this square.
+    fn remove_for_square(&mut self, square: Square) {
+        match (square.rank(), square.file()) {
+            (0, 0) => self.white_queenside = false,
+            (0, 4) => {
+                self.white_kingside = false;
+                self.white_queenside = false;
+            }
+            (0, 7) => self.white_kingside = false,
+            (7, 0) => self.black_queenside = false,
+            (7, 4) => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+            (7, 7) => self.black_kingside = false,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    InProgress,
+    /// The side to move is checkmated.
+    Checkmate,
+    Stalemate,
+}
+
+/// Everything `Position::unmake_move` needs to take a move back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Undo {
+    captured: Option<(Square, Piece)>,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    key: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Position {
+    /// What's on each square, for quick lookups of a single square.
+    pub(crate) board: [Option<Piece>; 64],
+    /// The squares occupied by each color and each piece type, for move generation.
+    pub(crate) by_color: [Bitboard; 2],
+    pub(crate) by_type: [Bitboard; 6],
+    pub(crate) side_to_move: PieceColor,
+    pub(crate) castling_rights: CastlingRights,
+    /// The square skipped over by a pawn's double push on the previous move.
+    pub(crate) en_passant: Option<Square>,
+    /// Moves since the last capture or pawn move.
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    /// Zobrist key of the pieces, castling rights and side to move, kept up to date as pieces
+    /// move. `zobrist_key` adds the en passant file.
+    pub(crate) key: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::starting()
+    }
+}
+
+impl Position {
+    /// An empty board with White to move and no castling rights.
+    pub fn empty() -> Self {
+        Position {
+            board: [None; 64],
+            by_color: [Bitboard::EMPTY; 2],
+            by_type: [Bitboard::EMPTY; 6],
+            side_to_move: PieceColor::White,
+            castling_rights: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            key: zobrist::white_to_move(),
+        }
+    }
+
+    pub fn starting() -> Self {
+        let back_row = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+            PieceType::King,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+        ];
+
+        let mut position = Position::empty();
+        for (file, piece_type) in back_row.iter().enumerate() {
+            let file = file as u8;
+            position.put_piece(
+                Square::from_rank_file(0, file),
+                Piece::new(PieceColor::White, *piece_type),
+            );
+            position.put_piece(
+                Square::from_rank_file(1, file),
+                Piece::new(PieceColor::White, PieceType::Pawn),
+            );
+            position.put_piece(
+                Square::from_rank_file(6, file),
+                Piece::new(PieceColor::Black, PieceType::Pawn),
+            );
+            position.put_piece(
+                Square::from_rank_file(7, file),
+                Piece::new(PieceColor::Black, *piece_type),
+            );
+        }
+        position.castling_rights = CastlingRights::all();
+        position.refresh_key();
+        position
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.board[square.index()]
+    }
+
+    /// Iterates over every occupied square.
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.occupied()
+            .map(move |square| (square, self.board[square.index()].unwrap()))
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    /// The squares occupied by pieces of the given color.
+    pub fn color_bitboard(&self, color: PieceColor) -> Bitboard {
+        self.by_color[color.index()]
+    }
+
+    /// The squares occupied by pieces of the given color and type.
+    pub fn piece_bitboard(&self, color: PieceColor, piece_type: PieceType) -> Bitboard {
+        self.by_color[color.index()] & self.by_type[piece_type.index()]
+    }
+
+    pub(crate) fn put_piece(&mut self, square: Square, piece: Piece) {
+        let bit = Bitboard::from_square(square);
+        self.board[square.index()] = Some(piece);
+        self.key ^= zobrist::piece(piece, square);
+        self.by_color[piece.color.index()] |= bit;
+        self.by_type[piece.piece_type.index()] |= bit;
+    }
+
+    fn remove_piece(&mut self, square: Square) -> Option<Piece> {
+        let piece = self.board[square.index()].take()?;
+        let bit = Bitboard::from_square(square);
+        self.key ^= zobrist::piece(piece, square);
+        self.by_color[piece.color.index()] ^= bit;
+        self.by_type[piece.piece_type.index()] ^= bit;
+        Some(piece)
+    }
+
+    pub fn side_to_move(&self) -> PieceColor {
+        self.side_to_move
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        self.piece_bitboard(color, PieceType::King).first()
+    }
+
+    /// Returns true if any piece of the given color is attacking the square.
+    pub fn is_attacked(&self, square: Square, by_color: PieceColor) -> bool {
+        !self.attackers(square, by_color).is_empty()
+    }
+
+    /// The pieces of the given color attacking the square. Works backwards from the square: a
+    /// knight there would attack exactly the knights attacking it, and so on.
+    pub fn attackers(&self, square: Square, by_color: PieceColor) -> Bitboard {
+        let occupied = self.occupied();
+        let queens = self.piece_bitboard(by_color, PieceType::Queen);
+        (attacks::pawn_attacks(by_color.opposite(), square)
+            & self.piece_bitboard(by_color, PieceType::Pawn))
+            | (attacks::knight_attacks(square) & self.piece_bitboard(by_color, PieceType::Knight))
+            | (attacks::king_attacks(square) & self.piece_bitboard(by_color, PieceType::King))
+            | (attacks::bishop_attacks(square, occupied)
+                & (self.piece_bitboard(by_color, PieceType::Bishop) | queens))
+            | (attacks::rook_attacks(square, occupied)
+                & (self.piece_bitboard(by_color, PieceType::Rook) | queens))
+    }
+
+    /// Returns true if the side to move is in check.
+    pub fn is_check(&self) -> bool {
+        self.is_in_check(self.side_to_move)
+    }
+
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        match self.king_square(color) {
+            Some(king) => self.is_attacked(king, color.opposite()),
+            None => false,
+        }
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if !self.legal_moves().is_empty() {
+            GameStatus::InProgress
+        } else if self.is_check() {
+            GameStatus::Checkmate
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    /// Whether the given side can't possibly checkmate, even with the other side's help: a lone
+    /// king, a king and one knight, or bishops that all stand on squares of one color.
+    pub fn has_insufficient_material(&self, color: PieceColor) -> bool {
+        let own = |piece_type| self.piece_bitboard(color, piece_type);
+        if !(own(PieceType::Pawn) | own(PieceType::Rook) | own(PieceType::Queen)).is_empty() {
+            return false;
+        }
+
+        let opponent = self.color_bitboard(color.opposite())
+            & !self.piece_bitboard(color.opposite(), PieceType::King)
+            & !self.piece_bitboard(color.opposite(), PieceType::Queen);
+        if !own(PieceType::Knight).is_empty() {
+            // A knight can only mate a king boxed in by its own pieces, and queens can't be
+            // boxed in without giving check
+            return self.color_bitboard(color).count() <= 2 && opponent.is_empty();
+        }
+
+        if !own(PieceType::Bishop).is_empty() {
+            // Bishops on one color never attack the other color's squares, so it takes a pawn or
+            // knight in the way to mate with them
+            let bishops = self.by_type[PieceType::Bishop.index()];
+            let on_light_squares = bishops
+                .filter(|square| (square.rank() + square.file()) % 2 == 1)
+                .count() as u32;
+            let same_color = on_light_squares == 0 || on_light_squares == bishops.count();
+            return same_color
+                && self.by_type[PieceType::Pawn.index()].is_empty()
+                && self.by_type[PieceType::Knight.index()].is_empty();
+        }
+
+        true
+    }
+
+    /// The position's Polyglot Zobrist key, the same for positions that are the same under the
+    /// repetition rules. The en passant file only counts if a pawn could take there.
+    pub fn zobrist_key(&self) -> u64 {
+        match self.en_passant {
+            Some(square)
+                if !(attacks::pawn_attacks(self.side_to_move.opposite(), square)
+                    & self.piece_bitboard(self.side_to_move, PieceType::Pawn))
+                .is_empty() =>
+            {
+                self.key ^ zobrist::en_passant_file(square.file())
+            }
+            _ => self.key,
+        }
+    }
+
+    /// Works out the key from scratch, after setting up a position field by field.
+    pub(crate) fn refresh_key(&mut self) {
+        let mut key = zobrist::castling(self.castling_rights);
+        if self.side_to_move == PieceColor::White {
+            key ^= zobrist::white_to_move();
+        }
+        for (square, piece) in self.pieces() {
+            key ^= zobrist::piece(piece, square);
+        }
+        self.key = key;
+    }
+
+    /// Returns every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        movegen::legal_moves(self)
+    }
+
+    /// Returns the legal moves of the piece on the square.
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Move> {
+        movegen::legal_moves_from(self, from)
+    }
+
+    /// Returns true if the side to move can play this move.
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves_from(mv.from).contains(&mv)
+    }
+
+    /// The king moves two squares towards a rook that hasn't moved yet, and can't castle out
+    /// of, through, or into check.
+    pub(crate) fn can_castle(&self, kingside: bool) -> bool {
+        let color = self.side_to_move;
+        let home_rank = match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 7,
+        };
+        let king_square = Square::from_rank_file(home_rank, 4);
+        let rook_square = Square::from_rank_file(home_rank, if kingside { 7 } else { 0 });
+        if !self.castling_rights.has(color, kingside)
+            || self.piece_at(king_square) != Some(Piece::new(color, PieceType::King))
+            || self.piece_at(rook_square) != Some(Piece::new(color, PieceType::Rook))
+            || !(attacks::between(king_square, rook_square) & self.occupied()).is_empty()
+        {
+            return false;
+        }
+
+        let passing_file = if kingside { 5 } else { 3 };
+        let to_file = if kingside { 6 } else { 2 };
+        [4, passing_file, to_file].iter().all(|file| {
+            !self.is_attacked(Square::from_rank_file(home_rank, *file), color.opposite())
+        })
+    }
+
+    /// Plays the move, which has to be legal, and returns what is needed to take it back.
+    pub fn make_move(&mut self, mv: Move) -> Undo {
+        let undo = Undo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            key: self.key,
+        };
+        let piece = self
+            .remove_piece(mv.from)
+            .expect("no piece on the square being moved from");
+
+        // A pawn capturing en passant takes the pawn beside the destination, not the one on it
+        let captured_square = if piece.piece_type == PieceType::Pawn
+            && self.en_passant == Some(mv.to)
+            && mv.from.file() != mv.to.file()
+        {
+            Square::from_rank_file(mv.from.rank(), mv.to.file())
+        } else {
+            mv.to
+        };
+        let captured = self
+            .remove_piece(captured_square)
+            .map(|captured| (captured_square, captured));
+
+        self.put_piece(
+            mv.to,
+            match mv.promotion {
+                Some(piece_type) => Piece::new(piece.color, piece_type),
+                None => piece,
+            },
+        );
+
+        // A king moving two squares is castling, so the rook has to come along
+        if piece.piece_type == PieceType::King
+            && (mv.to.file() as i8 - mv.from.file() as i8).abs() == 2
+        {
+            let (rook_from, rook_to) = castling_rook_squares(mv);
+            let rook = self
+                .remove_piece(rook_from)
+                .expect("no rook to castle with");
+            self.put_piece(rook_to, rook);
+        }
+
+        self.key ^= zobrist::castling(self.castling_rights);
+        self.castling_rights.remove_for_square(mv.from);
+        self.castling_rights.remove_for_square(mv.to);
+        self.key ^= zobrist::castling(self.castling_rights);
+
+        // Remember a double pawn push so it can be captured en passant next move
+        self.en_passant = if piece.piece_type == PieceType::Pawn
+            && (mv.to.rank() as i8 - mv.from.rank() as i8).abs() == 2
+        {
+            Some(Square::from_rank_file(
+                (mv.from.rank() + mv.to.rank()) / 2,
+                mv.from.file(),
+            ))
+        } else {
+            None
+        };
+
+        if piece.piece_type == PieceType::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.side_to_move == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = self.side_to_move.opposite();
+        self.key ^= zobrist::white_to_move();
+
+        Undo { captured, ..undo }
+    }
+
+    /// Takes back a move made with `make_move`.
+    pub fn unmake_move(&mut self, mv: Move, undo: Undo) {
+        self.side_to_move = self.side_to_move.opposite();
+        if self.side_to_move == PieceColor::Black {
+            self.fullmove_number -= 1;
+        }
+
+        let piece = self
+            .remove_piece(mv.to)
+            .expect("no piece on the square being moved back from");
+        self.put_piece(
+            mv.from,
+            match mv.promotion {
+                Some(_) => Piece::new(piece.color, PieceType::Pawn),
+                None => piece,
+            },
+        );
+
+        if piece.piece_type == PieceType::King
+            && (mv.to.file() as i8 - mv.from.file() as i8).abs() == 2
+        {
+            let (rook_from, rook_to) = castling_rook_squares(mv);
+            let rook = self.remove_piece(rook_to).expect("no rook to uncastle");
+            self.put_piece(rook_from, rook);
+        }
+
+        if let Some((square, captured)) = undo.captured {
+            self.put_piece(square, captured);
+        }
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.key = undo.key;
+    }
+}
+
+/// Returns where the rook starts and ends when the king castles with this move.
+fn castling_rook_squares(king_move: Move) -> (Square, Square) {
+    let rank = king_move.from.rank();
+    if king_move.to.file() > king_move.from.file() {
+        (
+            Square::from_rank_file(rank, 7),
+            Square::from_rank_file(rank, 5),
+        )
+    } else {
+        (
+            Square::from_rank_file(rank, 0),
+            Square::from_rank_file(rank, 3),
+        )
+    }
+}
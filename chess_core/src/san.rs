@@ -0,0 +1,259 @@
+//! Standard Algebraic Notation, e.g. `Nbd7`, `exd5`, `e8=Q+` or `O-O-O#`, and Long Algebraic
+//! Notation which always names the square moved from, e.g. `Nb8-d7` or `e4xd5`.
+
+use std::{error::Error, fmt};
+
+use crate::{GameStatus, Move, PieceType, Position, Square};
+
+/// The letter for a piece type as used in SAN. Pawns don't have one.
+pub fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::King => "K",
+        PieceType::Queen => "Q",
+        PieceType::Bishop => "B",
+        PieceType::Knight => "N",
+        PieceType::Rook => "R",
+        PieceType::Pawn => "",
+    }
+}
+
+/// Formats a legal move in the given position as SAN.
+pub fn to_san(position: &Position, mv: Move) -> String {
+    let piece = position
+        .piece_at(mv.from)
+        .expect("no piece on the square being moved from");
+    let file_diff = mv.to.file() as i8 - mv.from.file() as i8;
+
+    let san = if let Some(castling) = castling_notation(piece.piece_type, file_diff) {
+        castling.to_string()
+    } else {
+        let is_capture = position.piece_at(mv.to).is_some()
+            || (piece.piece_type == PieceType::Pawn && file_diff != 0);
+        let mut san = piece_letter(piece.piece_type).to_string();
+
+        if piece.piece_type == PieceType::Pawn {
+            if is_capture {
+                san.push((b'a' + mv.from.file()) as char);
+            }
+        } else {
+            san.push_str(&disambiguation(position, mv, piece.piece_type));
+        }
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&mv.to.to_string());
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+        san
+    };
+    san + check_suffix(position, mv)
+}
+
+/// Formats a legal move in the given position as LAN.
+pub fn to_lan(position: &Position, mv: Move) -> String {
+    let piece = position
+        .piece_at(mv.from)
+        .expect("no piece on the square being moved from");
+    let file_diff = mv.to.file() as i8 - mv.from.file() as i8;
+
+    let lan = if let Some(castling) = castling_notation(piece.piece_type, file_diff) {
+        castling.to_string()
+    } else {
+        let is_capture = position.piece_at(mv.to).is_some()
+            || (piece.piece_type == PieceType::Pawn && file_diff != 0);
+        let mut lan = format!(
+            "{}{}{}{}",
+            piece_letter(piece.piece_type),
+            mv.from,
+            if is_capture { 'x' } else { '-' },
+            mv.to
+        );
+        if let Some(promotion) = mv.promotion {
+            lan.push('=');
+            lan.push_str(piece_letter(promotion));
+        }
+        lan
+    };
+    lan + check_suffix(position, mv)
+}
+
+fn castling_notation(piece_type: PieceType, file_diff: i8) -> Option<&'static str> {
+    match (piece_type, file_diff) {
+        (PieceType::King, 2) => Some("O-O"),
+        (PieceType::King, -2) => Some("O-O-O"),
+        _ => None,
+    }
+}
+
+/// `+` if the move gives check, `#` if it gives mate.
+fn check_suffix(position: &Position, mv: Move) -> &'static str {
+    let mut after_move = position.clone();
+    after_move.make_move(mv);
+    if !after_move.is_check() {
+        ""
+    } else if after_move.status() == GameStatus::Checkmate {
+        "#"
+    } else {
+        "+"
+    }
+}
+
+/// When several pieces of the same type can move to the same square, the file, rank or both
+/// of the moving piece are added to tell them apart.
+fn disambiguation(position: &Position, mv: Move, piece_type: PieceType) -> String {
+    let others: Vec<Move> = position
+        .legal_moves()
+        .into_iter()
+        .filter(|other| {
+            other.to == mv.to
+                && other.from != mv.from
+                && position.piece_at(other.from).map(|piece| piece.piece_type) == Some(piece_type)
+        })
+        .collect();
+
+    if others.is_empty() {
+        String::new()
+    } else if others
+        .iter()
+        .all(|other| other.from.file() != mv.from.file())
+    {
+        ((b'a' + mv.from.file()) as char).to_string()
+    } else if others
+        .iter()
+        .all(|other| other.from.rank() != mv.from.rank())
+    {
+        ((b'1' + mv.from.rank()) as char).to_string()
+    } else {
+        mv.from.to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// Not something that looks like a move.
+    Invalid(String),
+    /// Looks like a move, but no legal move matches it.
+    Illegal(String),
+    /// More than one legal move matches it.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "`{}` is not a valid move", san),
+            SanError::Illegal(san) => write!(f, "illegal move `{}`", san),
+            SanError::Ambiguous(san) => write!(f, "ambiguous move `{}`", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+fn piece_type_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'K' => Some(PieceType::King),
+        'Q' => Some(PieceType::Queen),
+        'B' => Some(PieceType::Bishop),
+        'N' => Some(PieceType::Knight),
+        'R' => Some(PieceType::Rook),
+        _ => None,
+    }
+}
+
+/// Finds the legal move a SAN string refers to in the given position. LAN like `Ng1-f3` and UCI
+/// like `g1f3` or `e7e8q` are accepted too. Check marks and annotations like `!?` are ignored.
+pub fn parse_san(position: &Position, san: &str) -> Result<Move, SanError> {
+    let invalid = || SanError::Invalid(san.to_string());
+    let text = san.trim().trim_end_matches(|c| "+#!?".contains(c));
+
+    // Castling, also accepting zeros as some programs write it that way
+    let castling_file_diff = match text {
+        "O-O" | "0-0" => Some(2),
+        "O-O-O" | "0-0-0" => Some(-2),
+        _ => None,
+    };
+    if let Some(file_diff) = castling_file_diff {
+        return position
+            .legal_moves()
+            .into_iter()
+            .find(|mv| {
+                position.piece_at(mv.from).map(|piece| piece.piece_type) == Some(PieceType::King)
+                    && mv.to.file() as i8 - mv.from.file() as i8 == file_diff
+            })
+            .ok_or_else(|| SanError::Illegal(san.to_string()));
+    }
+
+    let mut chars: Vec<char> = text.chars().collect();
+    let piece_type = match chars.first().copied().and_then(piece_type_from_letter) {
+        Some(piece_type) => {
+            chars.remove(0);
+            Some(piece_type)
+        }
+        None => None,
+    };
+
+    // Promotions are written `e8=Q`, sometimes just `e8Q`, or `e7e8q` in UCI
+    let mut promotion = None;
+    if piece_type.is_none() {
+        if let Some(piece_type) = chars
+            .last()
+            .and_then(|c| piece_type_from_letter(c.to_ascii_uppercase()))
+        {
+            promotion = Some(piece_type);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    if chars.len() < 2 {
+        return Err(invalid());
+    }
+    let to: Square = chars[chars.len() - 2..]
+        .iter()
+        .collect::<String>()
+        .parse()
+        .map_err(|_| invalid())?;
+
+    // Whatever is left tells apart pieces that can reach the same square
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' => from_file = Some(*c as u8 - b'a'),
+            '1'..='8' => from_rank = Some(*c as u8 - b'1'),
+            'x' | ':' | '-' => {}
+            _ => return Err(invalid()),
+        }
+    }
+
+    // Without a piece letter it's a pawn move, unless the whole square moved from is given as in
+    // UCI
+    let piece_type = match (piece_type, from_file, from_rank) {
+        (Some(piece_type), _, _) => Some(piece_type),
+        (None, Some(_), Some(_)) => None,
+        (None, _, _) => Some(PieceType::Pawn),
+    };
+
+    let candidates: Vec<Move> = position
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| {
+            let moved_type = position.piece_at(mv.from).map(|piece| piece.piece_type);
+            mv.to == to
+                && mv.promotion == promotion
+                && (piece_type.is_none() || moved_type == piece_type)
+                && from_file.unwrap_or(mv.from.file()) == mv.from.file()
+                && from_rank.unwrap_or(mv.from.rank()) == mv.from.rank()
+        })
+        .collect();
+    match candidates.len() {
+        0 => Err(SanError::Illegal(san.to_string())),
+        1 => Ok(candidates[0]),
+        _ => Err(SanError::Ambiguous(san.to_string())),
+    }
+}
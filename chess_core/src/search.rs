@@ -0,0 +1,372 @@
+//! Iterative deepening alpha-beta search with a quiescence search on captures.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    eval,
+    tablebase::{Tablebase, Wdl},
+    Move, PieceType, Position, Undo,
+};
+
+/// Score for delivering mate right now. Mates further away score a little less, so the search
+/// prefers the quickest mate and the slowest loss.
+pub const MATE_SCORE: i32 = 100_000;
+
+/// Score for a position the tablebases say is won, less the plies to get there. Above any
+/// evaluation but below any mate.
+pub const TABLEBASE_WIN_SCORE: i32 = 20_000;
+
+/// Deepest the search ever goes, so mates found early don't make it search forever.
+pub const MAX_DEPTH: u32 = 64;
+
+/// How often, in nodes, the search looks at the clock and the stop flag. A power of two so the
+/// check is a cheap mask.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// When to stop searching. Depth 1 is always finished so there is a move to play.
+#[derive(Clone, Debug)]
+pub struct SearchLimits {
+    pub depth: u32,
+    pub movetime: Option<Duration>,
+    /// Set from another thread to stop the search early.
+    pub stop: Option<Arc<AtomicBool>>,
+    /// Endgame tables to look up positions with few pieces in.
+    pub tablebase: Option<Arc<Tablebase>>,
+    /// `Position::zobrist_key` of each position in the game so far, ending with the one searched
+    /// from, as in `Game::keys`. Positions that come up again are scored as draws.
+    pub history: Vec<u64>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            depth: MAX_DEPTH,
+            movetime: None,
+            stop: None,
+            tablebase: None,
+            history: Vec::new(),
+        }
+    }
+}
+
+/// The outcome of the deepest iteration that finished.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    /// Centipawns for the side to move, or `MATE_SCORE` minus the plies to mate.
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    /// Moves until mate, negative when the side to move is getting mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        let plies = MATE_SCORE - self.score.abs();
+        if plies > MAX_DEPTH as i32 {
+            None
+        } else if self.score > 0 {
+            Some((plies + 1) / 2)
+        } else {
+            Some(-(plies + 1) / 2)
+        }
+    }
+}
+
+struct Searcher<'a> {
+    limits: &'a SearchLimits,
+    started: Instant,
+    nodes: u64,
+    /// Keys of the game's positions and then the ones on the way to the position being searched.
+    keys: Vec<u64>,
+    /// Depth 1 always finishes so there is a move to play.
+    can_stop: bool,
+    stopped: bool,
+}
+
+impl Searcher<'_> {
+    fn should_stop(&mut self) -> bool {
+        if self.can_stop && !self.stopped && self.nodes & (CHECK_INTERVAL - 1) == 0 {
+            let out_of_time = match self.limits.movetime {
+                Some(movetime) => self.started.elapsed() >= movetime,
+                None => false,
+            };
+            let told_to_stop = match &self.limits.stop {
+                Some(stop) => stop.load(Ordering::Relaxed),
+                None => false,
+            };
+            self.stopped = out_of_time || told_to_stop;
+        }
+        self.stopped
+    }
+
+    fn make_move(&mut self, position: &mut Position, mv: Move) -> Undo {
+        let undo = position.make_move(mv);
+        self.keys.push(position.zobrist_key());
+        undo
+    }
+
+    fn unmake_move(&mut self, position: &mut Position, mv: Move, undo: Undo) {
+        position.unmake_move(mv, undo);
+        self.keys.pop();
+    }
+
+    /// Whether the position, the last in `keys`, came up before. Nothing before the last capture
+    /// or pawn move can be the same position.
+    fn is_repetition(&self, position: &Position) -> bool {
+        let (key, earlier) = self.keys.split_last().expect("the position's key");
+        let reversible = (position.halfmove_clock() as usize).min(earlier.len());
+        earlier[earlier.len() - reversible..].contains(key)
+    }
+
+    /// Searches every root move in order, returning the best one unless the search was stopped
+    /// before finishing the first.
+    fn root(&mut self, position: &mut Position, moves: &[Move], depth: u32) -> Option<(Move, i32)> {
+        let mut best = None;
+        let mut alpha = -MATE_SCORE - 1;
+        for mv in moves {
+            let undo = self.make_move(position, *mv);
+            let score = -self.alpha_beta(position, depth - 1, 1, -MATE_SCORE - 1, -alpha);
+            self.unmake_move(position, *mv, undo);
+            if self.stopped {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                best = Some((*mv, score));
+            }
+        }
+        best
+    }
+
+    fn alpha_beta(
+        &mut self,
+        position: &mut Position,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        // Whoever is worse off can at least repeat the position again, so call it a draw
+        if self.is_repetition(position) {
+            return 0;
+        }
+
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return if position.is_check() {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        if position.halfmove_clock() >= 100 {
+            return 0;
+        }
+
+        // Right after a capture or pawn move the fifty-move count starts over, so the
+        // tablebases have the exact result
+        if let Some(tablebase) = &self.limits.tablebase {
+            if position.halfmove_clock() == 0 {
+                match tablebase.probe_wdl(position) {
+                    Some(Wdl::Win) => return TABLEBASE_WIN_SCORE - ply as i32,
+                    Some(Wdl::Loss) => return -TABLEBASE_WIN_SCORE + ply as i32,
+                    Some(_) => return 0,
+                    None => {}
+                }
+            }
+        }
+
+        // Look one move further when in check so the search doesn't stop right before a mate
+        let depth = if position.is_check() {
+            depth + 1
+        } else {
+            depth
+        };
+        if depth == 0 || ply >= MAX_DEPTH {
+            return self.quiescence(position, alpha, beta);
+        }
+
+        order_moves(position, &mut moves);
+        for mv in moves {
+            let undo = self.make_move(position, mv);
+            let score = -self.alpha_beta(position, depth - 1, ply + 1, -beta, -alpha);
+            self.unmake_move(position, mv, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+
+    /// Keeps playing captures until the position is quiet, so the evaluation isn't taken in the
+    /// middle of an exchange.
+    fn quiescence(&mut self, position: &mut Position, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        // Standing pat: the side to move doesn't have to capture
+        let stand_pat = eval::evaluate(position);
+        if stand_pat >= beta {
+            return beta;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures: Vec<Move> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| is_capture(position, *mv) || mv.promotion == Some(PieceType::Queen))
+            .collect();
+        order_moves(position, &mut captures);
+        for mv in captures {
+            let undo = position.make_move(mv);
+            let score = -self.quiescence(position, -beta, -alpha);
+            position.unmake_move(mv, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+        alpha
+    }
+}
+
+pub(crate) fn is_capture(position: &Position, mv: Move) -> bool {
+    position.piece_at(mv.to).is_some()
+        || (position.en_passant() == Some(mv.to)
+            && position.piece_at(mv.from).map(|piece| piece.piece_type) == Some(PieceType::Pawn))
+}
+
+/// Puts promotions and captures of valuable pieces by cheap ones first, which lets alpha-beta
+/// cut off more of the tree.
+fn order_moves(position: &Position, moves: &mut [Move]) {
+    moves.sort_by_cached_key(|mv| {
+        let victim = match position.piece_at(mv.to) {
+            Some(piece) => eval::PIECE_VALUES[piece.piece_type.index()],
+            None if is_capture(position, *mv) => eval::PIECE_VALUES[PieceType::Pawn.index()],
+            None => 0,
+        };
+        let attacker = position
+            .piece_at(mv.from)
+            .map_or(0, |piece| eval::PIECE_VALUES[piece.piece_type.index()]);
+        let promotion = mv
+            .promotion
+            .map_or(0, |piece_type| eval::PIECE_VALUES[piece_type.index()]);
+        let score = if victim > 0 {
+            10 * victim - attacker / 10
+        } else {
+            0
+        };
+        -(score + promotion)
+    });
+}
+
+/// Finds the best move for the side to move, searching one ply deeper at a time until a limit
+/// is reached. Returns `None` when there are no legal moves.
+pub fn search(position: &Position, limits: &SearchLimits) -> Option<SearchResult> {
+    search_with_progress(position, limits, |_| {})
+}
+
+/// Like `search`, calling `progress` with the result of each depth as it finishes.
+pub fn search_with_progress(
+    position: &Position,
+    limits: &SearchLimits,
+    mut progress: impl FnMut(&SearchResult),
+) -> Option<SearchResult> {
+    let mut position = position.clone();
+    let mut moves = position.legal_moves();
+    if moves.is_empty() {
+        return None;
+    }
+    order_moves(&position, &mut moves);
+
+    // With few enough pieces, play the move the tablebases rank best, or search only the moves
+    // that keep a drawn position drawn
+    let ranked = (limits.tablebase.as_ref()).and_then(|tablebase| tablebase.rank_moves(&position));
+    if let Some(ranked) = ranked {
+        let best = ranked[0];
+        if best.rank != 0 {
+            let result = SearchResult {
+                best_move: best.mv,
+                score: match best.rank {
+                    1000 => TABLEBASE_WIN_SCORE - best.dtz,
+                    -1000 => -TABLEBASE_WIN_SCORE - best.dtz,
+                    _ => 0,
+                },
+                depth: 1,
+                nodes: 0,
+            };
+            progress(&result);
+            return Some(result);
+        }
+        moves.retain(|mv| {
+            ranked
+                .iter()
+                .any(|ranked_move| ranked_move.mv == *mv && ranked_move.rank == 0)
+        });
+    }
+
+    let mut keys = limits.history.clone();
+    if keys.last() != Some(&position.zobrist_key()) {
+        keys.push(position.zobrist_key());
+    }
+    let mut searcher = Searcher {
+        limits,
+        started: Instant::now(),
+        nodes: 0,
+        keys,
+        can_stop: false,
+        stopped: false,
+    };
+    let mut result = None;
+    for depth in 1..=limits.depth.max(1) {
+        // The previous best move is searched first, so even a search that was cut short has
+        // weighed it against whatever it picked
+        let (best_move, score) = match searcher.root(&mut position, &moves, depth) {
+            Some(best) => best,
+            None => break,
+        };
+        let depth_result = SearchResult {
+            best_move,
+            score,
+            depth,
+            nodes: searcher.nodes,
+        };
+        progress(&depth_result);
+        result = Some(depth_result);
+        searcher.can_stop = true;
+        if searcher.stopped || score.abs() >= MATE_SCORE - depth as i32 {
+            break;
+        }
+
+        // Search the best move first next time
+        let idx = moves.iter().position(|mv| *mv == best_move).unwrap();
+        moves[..=idx].rotate_right(1);
+    }
+    result
+}
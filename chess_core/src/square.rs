@@ -0,0 +1,79 @@
+use std::{fmt, str::FromStr};
+
+/// A square on the board, from a1 = 0 to h8 = 63.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Square(u8);
+
+impl Square {
+    pub fn new(index: u8) -> Self {
+        debug_assert!(index < 64);
+        Square(index)
+    }
+
+    /// Rank and file both go from 0 to 7, so e4 is rank 3, file 4.
+    pub fn from_rank_file(rank: u8, file: u8) -> Self {
+        debug_assert!(rank < 8 && file < 8);
+        Square(rank * 8 + file)
+    }
+
+    /// Like `from_rank_file`, but returns `None` when the coordinates fall off the board.
+    pub fn try_from_rank_file(rank: i8, file: i8) -> Option<Self> {
+        if (0..8).contains(&rank) && (0..8).contains(&file) {
+            Some(Square::from_rank_file(rank as u8, file as u8))
+        } else {
+            None
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    /// Returns the square offset by the given number of ranks and files, if it is on the board.
+    pub fn offset(self, ranks: i8, files: i8) -> Option<Self> {
+        Square::try_from_rank_file(self.rank() as i8 + ranks, self.file() as i8 + files)
+    }
+
+    /// Iterates over all 64 squares.
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file()) as char,
+            (b'1' + self.rank()) as char
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = ();
+
+    /// Parses a square name like `e4`.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 2 {
+            return Err(());
+        }
+        let file = bytes[0].wrapping_sub(b'a');
+        let rank = bytes[1].wrapping_sub(b'1');
+        if file < 8 && rank < 8 {
+            Ok(Square::from_rank_file(rank, file))
+        } else {
+            Err(())
+        }
+    }
+}
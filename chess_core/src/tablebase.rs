@@ -0,0 +1,1350 @@
+//! Syzygy endgame tablebases: `.rtbw` files say whether a position is won, drawn or lost, and
+//! `.rtbz` files how many plies it takes to get to the next capture or pawn move (DTZ) when
+//! playing it out. Each file is memory-mapped the first time it's probed. The file format
+//! and the way positions are turned into indices follow Stockfish's `tbprobe.cpp`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io,
+    ops::Neg,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use memmap2::Mmap;
+
+use crate::{
+    attacks, search::is_capture, Bitboard, CastlingRights, Move, Piece, PieceColor, PieceType,
+    Position, Square,
+};
+
+/// The most pieces in any Syzygy table.
+const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// Flags for each table in a file.
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// The result with best play for the side to move. Cursed wins and blessed losses are won or
+/// lost positions that the fifty-move rule turns into draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Option<Self> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+/// A legal move with the DTZ of the position before it when playing it, and how good it is
+/// considering the fifty-move rule: 1000 for a win, -1000 for a loss, 0 for a draw, and in
+/// between for wins and losses the fifty-move rule gets in the way of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RankedMove {
+    pub mv: Move,
+    pub dtz: i32,
+    pub rank: i32,
+}
+
+/// The tables in a directory, looked up by the pieces on the board.
+pub struct Tablebase {
+    dir: PathBuf,
+    /// Names of the WDL tables found, like `KQvK`.
+    available: HashSet<String>,
+    max_pieces: usize,
+    indices: Indices,
+    /// Tables opened so far by file name, `None` for files that couldn't be read. The lock is
+    /// only held to find a file's slot, so opening one table doesn't hold up probes of others.
+    tables: Mutex<HashMap<String, Arc<TableSlot>>>,
+}
+
+impl Default for Tablebase {
+    /// No tables, so every probe fails.
+    fn default() -> Self {
+        Tablebase {
+            dir: PathBuf::new(),
+            available: HashSet::new(),
+            max_pieces: 0,
+            indices: Indices::new(),
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("dir", &self.dir)
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+/// A table file, opened by whichever probe needs it first while any others wait for it.
+type TableSlot = OnceLock<Option<Arc<Table>>>;
+
+/// The value stored for a position, or for DTZ tables that only have the other side to move.
+enum Stored {
+    Value(i32),
+    OtherSideToMove,
+}
+
+impl Tablebase {
+    /// Finds the tables in the directory. They aren't read until they're probed.
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut tablebase = Tablebase {
+            dir: dir.as_ref().to_path_buf(),
+            ..Default::default()
+        };
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension() != Some("rtbw".as_ref()) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                let pieces = name.chars().filter(|c| *c != 'v').count();
+                tablebase.max_pieces = tablebase.max_pieces.max(pieces);
+                tablebase.available.insert(name.to_string());
+            }
+        }
+        Ok(tablebase)
+    }
+
+    /// The most pieces, kings included, a position can have to be in the tables.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Whether there could be tables for the position. Tables don't cover castling.
+    pub fn covers(&self, position: &Position) -> bool {
+        position.occupied().count() as usize <= self.max_pieces
+            && position.castling_rights() == CastlingRights::none()
+    }
+
+    /// Win, draw or loss for the side to move, `None` if the tables for the position or any
+    /// position a capture leads to are missing.
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.search(&mut position.clone(), false)
+            .map(|(wdl, _)| wdl)
+    }
+
+    /// Plies to the next capture or pawn move with best play, positive when the side to move
+    /// wins, negative when it loses and 0 for draws. Wins and losses the fifty-move rule turns
+    /// into draws are 100 further from 0.
+    pub fn probe_dtz(&self, position: &Position) -> Option<i32> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.dtz(&mut position.clone())
+    }
+
+    /// Every legal move ranked by the tables, best first. Among equally ranked moves the one
+    /// closest to the next capture or pawn move comes first when winning, and the furthest
+    /// when losing.
+    pub fn rank_moves(&self, position: &Position) -> Option<Vec<RankedMove>> {
+        if !self.covers(position) {
+            return None;
+        }
+
+        let mut position = position.clone();
+        let halfmove_clock = position.halfmove_clock() as i32;
+        let mut ranked = Vec::new();
+        for mv in position.legal_moves() {
+            let undo = position.make_move(mv);
+            let dtz = if position.halfmove_clock() == 0 {
+                dtz_before_zeroing(-self.search(&mut position, false)?.0)
+            } else {
+                let dtz = -self.dtz(&mut position)?;
+                dtz + dtz.signum()
+            };
+            // A mate is as close as it gets
+            let dtz = if dtz == 2 && position.is_check() && position.legal_moves().is_empty() {
+                1
+            } else {
+                dtz
+            };
+            position.unmake_move(mv, undo);
+
+            let rank = if dtz > 0 {
+                if dtz + halfmove_clock <= 99 {
+                    1000
+                } else {
+                    1000 - (dtz + halfmove_clock)
+                }
+            } else if dtz < 0 {
+                if -dtz * 2 + halfmove_clock < 100 {
+                    -1000
+                } else {
+                    -1000 + (-dtz + halfmove_clock)
+                }
+            } else {
+                0
+            };
+            ranked.push(RankedMove { mv, dtz, rank });
+        }
+        ranked.sort_by_key(|ranked_move| (-ranked_move.rank, ranked_move.dtz));
+        Some(ranked)
+    }
+
+    /// Looks at captures, and for DTZ pawn moves too, as well as the stored value, because
+    /// the tables don't store the right value for positions where those are best. Also says
+    /// whether such a move is the best one.
+    fn search(&self, position: &mut Position, zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = position.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for mv in moves.iter().copied() {
+            let is_pawn_move =
+                position.piece_at(mv.from).map(|piece| piece.piece_type) == Some(PieceType::Pawn);
+            if !(is_capture(position, mv) || zeroing_moves && is_pawn_move) {
+                continue;
+            }
+            searched += 1;
+
+            let undo = position.make_move(mv);
+            let value = self.search(position, false).map(|(wdl, _)| -wdl);
+            position.unmake_move(mv, undo);
+            let value = value?;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // With every move searched the stored value might be wrong, e.g. for en passant
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            self.probe_wdl_table(position)?
+        };
+        if best >= value {
+            Some((best, best > Wdl::Draw || all_searched))
+        } else {
+            Some((value, false))
+        }
+    }
+
+    fn dtz(&self, position: &mut Position) -> Option<i32> {
+        let (wdl, zeroing_is_best) = self.search(position, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_is_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_dtz_table(position, wdl)? {
+            Stored::Value(dtz) => {
+                let fifty_move_draw = wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss;
+                Some((dtz + if fifty_move_draw { 100 } else { 0 }) * wdl.signum())
+            }
+            Stored::OtherSideToMove => {
+                // Find the move with the best DTZ one ply further on
+                let mut min_dtz = i32::MAX;
+                for mv in position.legal_moves() {
+                    let zeroing = is_capture(position, mv)
+                        || position.piece_at(mv.from).map(|piece| piece.piece_type)
+                            == Some(PieceType::Pawn);
+                    let undo = position.make_move(mv);
+                    let dtz = if zeroing {
+                        self.search(position, false)
+                            .map(|(wdl, _)| -dtz_before_zeroing(wdl))
+                    } else {
+                        self.dtz(position).map(|dtz| -dtz)
+                    };
+                    let mates = position.is_check() && position.legal_moves().is_empty();
+                    position.unmake_move(mv, undo);
+
+                    let mut dtz = dtz?;
+                    if dtz == 1 && mates {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                        min_dtz = dtz;
+                    }
+                }
+                // No legal moves means mate
+                Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+            }
+        }
+    }
+
+    fn probe_wdl_table(&self, position: &Position) -> Option<Wdl> {
+        // Two bare kings have no table
+        if position.occupied().count() == 2 {
+            return Some(Wdl::Draw);
+        }
+        match self.probe_table(position, TableKind::Wdl, Wdl::Draw)? {
+            Stored::Value(value) => Wdl::from_value(value),
+            Stored::OtherSideToMove => None,
+        }
+    }
+
+    fn probe_dtz_table(&self, position: &Position, wdl: Wdl) -> Option<Stored> {
+        self.probe_table(position, TableKind::Dtz, wdl)
+    }
+
+    fn probe_table(&self, position: &Position, kind: TableKind, wdl: Wdl) -> Option<Stored> {
+        let white = material_name(position, PieceColor::White);
+        let black = material_name(position, PieceColor::Black);
+        // Tables have the stronger side first, and are written as if it were White
+        let (name, black_stronger) = if self.available.contains(&format!("{}v{}", white, black)) {
+            (format!("{}v{}", white, black), false)
+        } else {
+            (format!("{}v{}", black, white), true)
+        };
+        let table = self.table(&name, kind)?;
+        table.probe(position, &self.indices, black_stronger, wdl)
+    }
+
+    fn table(&self, name: &str, kind: TableKind) -> Option<Arc<Table>> {
+        if !self.available.contains(name) {
+            return None;
+        }
+        let file_name = format!("{}.{}", name, kind.extension());
+        let slot = self
+            .tables
+            .lock()
+            .unwrap()
+            .entry(file_name.clone())
+            .or_default()
+            .clone();
+        slot.get_or_init(|| {
+            let file = File::open(self.dir.join(&file_name)).ok()?;
+            // Safety: the tables are only read, and aren't expected to change while in use
+            let data = unsafe { Mmap::map(&file) }.ok()?;
+            Table::read(data, name, kind, &self.indices).map(Arc::new)
+        })
+        .clone()
+    }
+}
+
+/// The pieces of one side as they appear in table names, e.g. `KRP`.
+fn material_name(position: &Position, color: PieceColor) -> String {
+    let mut name = String::new();
+    for piece_type in &TABLE_PIECE_ORDER {
+        for _ in position.piece_bitboard(color, *piece_type) {
+            name.push(piece_letter(*piece_type));
+        }
+    }
+    name
+}
+
+const TABLE_PIECE_ORDER: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::King => 'K',
+        PieceType::Queen => 'Q',
+        PieceType::Rook => 'R',
+        PieceType::Bishop => 'B',
+        PieceType::Knight => 'N',
+        PieceType::Pawn => 'P',
+    }
+}
+
+/// How pieces are written in table files: the type from 1 for pawns to 6 for kings, plus 8 for
+/// Black.
+fn piece_code(piece: Piece) -> u8 {
+    let piece_type = match piece.piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight => 2,
+        PieceType::Bishop => 3,
+        PieceType::Rook => 4,
+        PieceType::Queen => 5,
+        PieceType::King => 6,
+    };
+    match piece.color {
+        PieceColor::White => piece_type,
+        PieceColor::Black => piece_type + 8,
+    }
+}
+
+/// DTZ tables don't store anything useful for positions where a capture or pawn move is best,
+/// but the DTZ right before one follows from the result.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+fn rank_of(square: usize) -> usize {
+    square >> 3
+}
+
+fn file_of(square: usize) -> usize {
+    square & 7
+}
+
+/// Which side of the a1-h8 diagonal the square is on: negative below, positive above.
+fn off_diagonal(square: usize) -> i32 {
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+/// Lookup tables for turning where the pieces are into an index into a table.
+struct Indices {
+    /// `binomial[k][n]` ways to pick k of n squares.
+    binomial: [[u64; 64]; MAX_PIECES],
+    /// Numbers a2-h7 from 47 down, so the leading pawn is the one with the highest number:
+    /// nearest the edge, then on the lowest rank.
+    map_pawns: [usize; 64],
+    /// Numbers the squares below the a1-h8 diagonal from 0 to 27.
+    map_b1h1h7: [usize; 64],
+    /// Numbers the a1-d1-d4 triangle from 0 to 9, with the diagonal last.
+    map_a1d1d4: [usize; 64],
+    /// The 462 ways to place two kings with the first in the a1-d1-d4 triangle.
+    map_kk: [[u64; 64]; 10],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+}
+
+impl Indices {
+    fn new() -> Self {
+        let mut indices = Indices {
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES],
+            lead_pawns_size: [[0; 4]; MAX_PIECES],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                indices.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..=27 {
+            if off_diagonal(square) < 0 && file_of(square) <= 3 {
+                indices.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 && file_of(square) <= 3 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            indices.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for idx in 0..10 {
+            for first in 0..=27 {
+                // b1 is the only square mapped to 0 that's in the triangle
+                if indices.map_a1d1d4[first] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+                let king_zone = attacks::king_attacks(Square::new(first as u8)).0 | 1 << first;
+                for second in 0..64 {
+                    if king_zone & 1 << second != 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        indices.map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            indices.map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        indices.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                indices.binomial[k][n] = if k > 0 {
+                    indices.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n { indices.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Leading pawns nearer the middle or further up leave other pawns fewer squares
+        let mut taken = 0;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        indices.map_pawns[square] = 47 - taken;
+                        indices.map_pawns[square ^ 7] = 46 - taken;
+                        taken += 2;
+                    }
+                    indices.lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += indices.binomial[lead_pawns - 1][indices.map_pawns[square]];
+                }
+                indices.lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        indices
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    fn extension(self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+}
+
+/// What the table's name says about its pieces.
+struct Material {
+    piece_count: usize,
+    has_pawns: bool,
+    /// Whether some side has exactly one of a piece other than the king.
+    has_unique_pieces: bool,
+    /// Pawns of the side that leads the encoding, then of the other side.
+    pawn_count: [usize; 2],
+    /// Both sides have the same pieces, so only White to move is stored.
+    symmetric: bool,
+}
+
+impl Material {
+    fn from_name(name: &str) -> Option<Self> {
+        let mut sides = name.split('v');
+        let (strong, weak) = (sides.next()?, sides.next()?);
+        let count = |side: &str, letter| side.chars().filter(|c| *c == letter).count();
+
+        let has_unique_pieces = ['Q', 'R', 'B', 'N', 'P']
+            .iter()
+            .any(|letter| count(strong, *letter) == 1 || count(weak, *letter) == 1);
+        let strong_pawns = count(strong, 'P');
+        let weak_pawns = count(weak, 'P');
+        // The side with fewer pawns leads, as that compresses better
+        let strong_leads = weak_pawns == 0 || (strong_pawns > 0 && weak_pawns >= strong_pawns);
+        let pawn_count = if strong_leads {
+            [strong_pawns, weak_pawns]
+        } else {
+            [weak_pawns, strong_pawns]
+        };
+
+        Some(Material {
+            piece_count: strong.len() + weak.len(),
+            has_pawns: strong_pawns + weak_pawns > 0,
+            has_unique_pieces,
+            pawn_count,
+            symmetric: strong == weak,
+        })
+    }
+}
+
+/// One of the files of a tablebase, mapped into memory so only the blocks probed get read from
+/// disk.
+struct Table {
+    data: Mmap,
+    kind: TableKind,
+    material: Material,
+    /// `[side to move][leading pawn's file]`, with one side when only one is stored and one
+    /// file when there are no pawns.
+    items: Vec<Vec<PairsData>>,
+}
+
+impl Table {
+    fn read(data: Mmap, name: &str, kind: TableKind, indices: &Indices) -> Option<Self> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.get(..4)? != magic {
+            return None;
+        }
+        let material = Material::from_name(name)?;
+
+        let header = *data.get(4)?;
+        if (header & 2 != 0) != material.has_pawns || (header & 1 != 0) == material.symmetric {
+            return None;
+        }
+        let mut at = 5;
+
+        let sides = if kind == TableKind::Wdl && !material.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_have_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut items = vec![vec![PairsData::default(); files]; sides];
+
+        for file in 0..files {
+            let first = *data.get(at)?;
+            let second = if both_have_pawns {
+                *data.get(at + 1)?
+            } else {
+                0xff
+            };
+            let orders = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            at += 1 + both_have_pawns as usize;
+
+            for k in 0..material.piece_count {
+                let byte = *data.get(at)?;
+                for (side, side_items) in items.iter_mut().enumerate() {
+                    side_items[file].pieces[k] = if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+                at += 1;
+            }
+            for (side, side_items) in items.iter_mut().enumerate() {
+                let order = [orders[side][0] as usize, orders[side][1] as usize];
+                side_items[file].set_groups(&material, order, file, indices);
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side_items in items.iter_mut() {
+                at = side_items[file].set_sizes(&data, at)?;
+            }
+        }
+
+        if kind == TableKind::Dtz {
+            for item in items[0].iter_mut() {
+                if item.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                for map_idx in item.map_idx.iter_mut() {
+                    if item.flags & FLAG_WIDE != 0 {
+                        at += at & 1;
+                        *map_idx = at + 2;
+                        at += 2 * read_u16_le(&data, at)? as usize + 2;
+                    } else {
+                        *map_idx = at + 1;
+                        at += *data.get(at)? as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for file in 0..files {
+            for side_items in items.iter_mut() {
+                side_items[file].sparse_index = at;
+                at += side_items[file].sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side_items in items.iter_mut() {
+                side_items[file].block_lengths = at;
+                at += side_items[file].block_lengths_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side_items in items.iter_mut() {
+                at = (at + 0x3f) & !0x3f;
+                side_items[file].data = at;
+                at += side_items[file].num_blocks * side_items[file].block_size;
+            }
+        }
+        if at > data.len() {
+            return None;
+        }
+
+        Some(Table {
+            data,
+            kind,
+            material,
+            items,
+        })
+    }
+
+    fn probe(
+        &self,
+        position: &Position,
+        indices: &Indices,
+        black_stronger: bool,
+        wdl: Wdl,
+    ) -> Option<Stored> {
+        let material = &self.material;
+        // Positions are looked up as if the stronger side were White and, for symmetric
+        // tables, as if White were to move
+        let flip =
+            black_stronger || (material.symmetric && position.side_to_move() == PieceColor::Black);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side_to_move = flip as usize ^ position.side_to_move().index();
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut lead_pawn_bitboard = Bitboard::EMPTY;
+        let mut file = 0;
+
+        if material.has_pawns {
+            // The leading pawns come first in every file's table
+            let pawn_code = self.items[0][0].pieces[0] ^ flip_color;
+            let color = if pawn_code & 8 == 0 {
+                PieceColor::White
+            } else {
+                PieceColor::Black
+            };
+            lead_pawn_bitboard = position.piece_bitboard(color, PieceType::Pawn);
+            for square in lead_pawn_bitboard {
+                squares[size] = square.index() ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns = size;
+            let leader = (0..lead_pawns).max_by_key(|i| indices.map_pawns[squares[*i]])?;
+            squares.swap(0, leader);
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        // DTZ tables only store one side to move, unless the sides are the same
+        if self.kind == TableKind::Dtz {
+            let stored_side = (self.items[0][file].flags & FLAG_STM) as usize;
+            let stores_both = material.symmetric && !material.has_pawns;
+            if stored_side != side_to_move && !stores_both {
+                return Some(Stored::OtherSideToMove);
+            }
+        }
+
+        for square in position.occupied() ^ lead_pawn_bitboard {
+            if size == MAX_PIECES {
+                return None;
+            }
+            squares[size] = square.index() ^ flip_squares;
+            pieces[size] = piece_code(position.piece_at(square)?) ^ flip_color;
+            size += 1;
+        }
+        if size != material.piece_count {
+            return None;
+        }
+
+        let d = &self.items[side_to_move % self.items.len()][file];
+
+        // Put the pieces in the order the table encodes them in
+        for i in lead_pawns..size - 1 {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // The leading piece goes on the queenside
+        if file_of(squares[0]) > 3 {
+            for square in squares[..size].iter_mut() {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx;
+        if material.has_pawns {
+            idx = indices.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|square| indices.map_pawns[*square]);
+            for (i, square) in squares[..lead_pawns].iter().enumerate().skip(1) {
+                idx += indices.binomial[i][indices.map_pawns[*square]];
+            }
+        } else {
+            // Without pawns the leading piece also goes below the fifth rank...
+            if rank_of(squares[0]) > 3 {
+                for square in squares[..size].iter_mut() {
+                    *square ^= 56;
+                }
+            }
+            // ...and the first of the leading group off the diagonal goes below it
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares[i..size].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            idx = if material.has_unique_pieces {
+                encode_unique_pieces(&squares, indices)
+            } else {
+                indices.map_kk[indices.map_a1d1d4[squares[0]]][squares[1]]
+            };
+        }
+
+        idx *= d.group_idx[0];
+        let mut group_start = d.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let group_end = group_start + d.group_len[next];
+            squares[group_start..group_end].sort_unstable();
+            let mut n = 0;
+            for (i, square) in squares[group_start..group_end].iter().enumerate() {
+                // Squares taken by earlier groups don't count
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|earlier| *square > **earlier)
+                    .count();
+                let pawn_offset = if remaining_pawns { 8 } else { 0 };
+                n += indices.binomial[i + 1][square - adjust - pawn_offset];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = d.decompress(&self.data, idx)?;
+        Some(Stored::Value(match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => self.map_dtz(file, value, wdl)?,
+        }))
+    }
+
+    /// DTZ values are stored renumbered by how common they are, and in moves rather than
+    /// plies when that loses nothing.
+    fn map_dtz(&self, file: usize, value: i32, wdl: Wdl) -> Option<i32> {
+        let d = &self.items[0][file];
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0 {
+            // Maps are stored for wins, losses, cursed wins and blessed losses
+            let map = match wdl {
+                Wdl::Win | Wdl::Draw => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                Wdl::BlessedLoss => 3,
+            };
+            let at = d.map_idx[map];
+            value = if d.flags & FLAG_WIDE != 0 {
+                read_u16_le(&self.data, at + 2 * value as usize)? as i32
+            } else {
+                *self.data.get(at + value as usize)? as i32
+            };
+        }
+
+        let in_moves = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+}
+
+/// Index of the first three pieces placed together, for tables where some side has a lone
+/// piece besides the king. The first piece is in the a1-d1-d4 triangle and pieces on the
+/// diagonal come last.
+fn encode_unique_pieces(squares: &[usize], indices: &Indices) -> u64 {
+    let (first, second, third) = (squares[0], squares[1], squares[2]);
+    let adjust1 = (second > first) as usize;
+    let adjust2 = (third > first) as usize + (third > second) as usize;
+    let idx = if off_diagonal(first) != 0 {
+        (indices.map_a1d1d4[first] * 63 + (second - adjust1)) * 62 + third - adjust2
+    } else if off_diagonal(second) != 0 {
+        (6 * 63 + rank_of(first) * 28 + indices.map_b1h1h7[second]) * 62 + third - adjust2
+    } else if off_diagonal(third) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank_of(first) * 7 * 28
+            + (rank_of(second) - adjust1) * 28
+            + indices.map_b1h1h7[third]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank_of(first) * 7 * 6
+            + (rank_of(second) - adjust1) * 6
+            + (rank_of(third) - adjust2)
+    };
+    idx as u64
+}
+
+/// One table of values in a file, for one side to move and leading pawn file. Values are
+/// compressed by replacing common pairs of symbols with new symbols, over and over, then
+/// Huffman coding the symbols into blocks.
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    /// For single value tables, the value.
+    min_sym_len: u8,
+    block_size: usize,
+    /// There's a sparse index entry about every `span` values.
+    span: usize,
+    num_blocks: usize,
+    /// Where things are in the file.
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_lengths: usize,
+    block_lengths_size: usize,
+    data: usize,
+    /// `base64[l - min_sym_len]` is the lowest symbol of length `l`, padded to 64 bits.
+    base64: Vec<u64>,
+    /// How many values, minus one, each symbol stands for.
+    symlen: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    /// Pieces in each group that's encoded together, ending with a 0.
+    group_len: [usize; MAX_PIECES + 1],
+    /// Where the DTZ map for each result starts in the file.
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    /// Groups pieces of the same type and color, except for the leading group which is the
+    /// first three pieces, or the two kings if there's no lone piece, or the leading pawns.
+    fn set_groups(
+        &mut self,
+        material: &Material,
+        order: [usize; 2],
+        file: usize,
+        indices: &Indices,
+    ) {
+        let mut n = 0;
+        let mut first_len: i32 = if material.has_pawns {
+            0
+        } else if material.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        self.group_len[0] = 1;
+        for i in 1..material.piece_count {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            } else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        // The groups are encoded in the order the file gives rather than the order above
+        let both_have_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = if both_have_pawns { 2 } else { 1 };
+        let mut free_squares = 64
+            - self.group_len[0]
+            - if both_have_pawns {
+                self.group_len[1]
+            } else {
+                0
+            };
+        let mut idx = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                self.group_idx[0] = idx;
+                idx *= if material.has_pawns {
+                    indices.lead_pawns_size[self.group_len[0]][file]
+                } else if material.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                self.group_idx[1] = idx;
+                idx *= indices.binomial[self.group_len[1]][48 - self.group_len[0]];
+            } else {
+                self.group_idx[next] = idx;
+                idx *= indices.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+    }
+
+    /// Reads the sizes and the symbol tree starting at `at`, returning where they end.
+    fn set_sizes(&mut self, data: &[u8], mut at: usize) -> Option<usize> {
+        self.flags = *data.get(at)?;
+        at += 1;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = *data.get(at)?;
+            return Some(at + 1);
+        }
+
+        let groups = self.group_len.iter().position(|len| *len == 0)?;
+        let table_size = self.group_idx[groups];
+        self.block_size = 1 << *data.get(at)?;
+        self.span = 1 << *data.get(at + 1)?;
+        self.sparse_index_size = table_size.div_ceil(self.span as u64) as usize;
+        let padding = *data.get(at + 2)? as usize;
+        self.num_blocks = read_u32_le(data, at + 3)? as usize;
+        // Padded so the sparse index never points past the end
+        self.block_lengths_size = self.num_blocks + padding;
+        let max_sym_len = *data.get(at + 7)?;
+        self.min_sym_len = *data.get(at + 8)?;
+        at += 9;
+        if max_sym_len < self.min_sym_len {
+            return None;
+        }
+
+        // Canonical Huffman codes: longer codes have lower values, so the lowest code of each
+        // length, padded to 64 bits, tells a code's length from its first bits
+        self.lowest_sym = at;
+        let lengths = (max_sym_len - self.min_sym_len) as usize + 1;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = read_u16_le(data, at + 2 * i)? as u64;
+            let next_lowest = read_u16_le(data, at + 2 * (i + 1))? as u64;
+            self.base64[i] = (self.base64[i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            let shift = 64 - i as u32 - self.min_sym_len as u32;
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+        at += lengths * 2;
+
+        let symbols = read_u16_le(data, at)? as usize;
+        at += 2;
+        self.btree = at;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            if !visited[symbol] {
+                self.symlen[symbol] = self.set_symlen(data, symbol, &mut visited)?;
+            }
+        }
+        Some(at + symbols * 3 + (symbols & 1))
+    }
+
+    fn set_symlen(&mut self, data: &[u8], symbol: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[symbol] = true;
+        let (left, right) = self.pair(data, symbol)?;
+        if right == 0xfff {
+            return Some(0);
+        }
+        for child in [left, right].iter().copied() {
+            if !*visited.get(child)? {
+                self.symlen[child] = self.set_symlen(data, child, visited)?;
+            }
+        }
+        Some(
+            self.symlen[left]
+                .wrapping_add(self.symlen[right])
+                .wrapping_add(1),
+        )
+    }
+
+    /// The two symbols a symbol stands for, 12 bits each. A symbol standing for one value has
+    /// the value on the left and 0xfff on the right.
+    fn pair(&self, data: &[u8], symbol: usize) -> Option<(usize, usize)> {
+        let bytes = data.get(self.btree + 3 * symbol..self.btree + 3 * symbol + 3)?;
+        let left = ((bytes[1] as usize & 0xf) << 8) | bytes[0] as usize;
+        let right = ((bytes[2] as usize) << 4) | (bytes[1] as usize >> 4);
+        Some((left, right))
+    }
+
+    fn block_length(&self, data: &[u8], block: usize) -> Option<i64> {
+        read_u16_le(data, self.block_lengths + 2 * block).map(i64::from)
+    }
+
+    /// The value at `idx`.
+    fn decompress(&self, data: &[u8], idx: u64) -> Option<i32> {
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(self.min_sym_len as i32);
+        }
+
+        // Each block holds a varying number of values, so start from the nearest sparse index
+        // entry and walk over blocks to the one holding idx
+        let entry = self.sparse_index + 6 * (idx / self.span as u64) as usize;
+        let mut block = read_u32_le(data, entry)? as usize;
+        let mut offset = read_u16_le(data, entry + 4)? as i64;
+        offset += (idx % self.span as u64) as i64 - (self.span / 2) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += self.block_length(data, block)? + 1;
+        }
+        while offset > self.block_length(data, block)? {
+            offset -= self.block_length(data, block)? + 1;
+            block += 1;
+        }
+
+        // Read Huffman codes until the one holding our value
+        let mut at = self.data + block * self.block_size;
+        let mut buf = read_u64_be(data, at)?;
+        at += 8;
+        let mut buf_size = 64;
+        let mut symbol = loop {
+            let mut len = 0;
+            while buf < *self.base64.get(len)? {
+                len += 1;
+            }
+            let shift = 64 - len as u32 - self.min_sym_len as u32;
+            let code = (buf - self.base64[len]).checked_shr(shift).unwrap_or(0) as u16;
+            let symbol = code.wrapping_add(read_u16_le(data, self.lowest_sym + 2 * len)?) as usize;
+            let values = *self.symlen.get(symbol)? as i64 + 1;
+            if offset < values {
+                break symbol;
+            }
+
+            offset -= values;
+            let len = len as u32 + self.min_sym_len as u32;
+            buf = buf.checked_shl(len).unwrap_or(0);
+            buf_size -= len as i32;
+            if buf_size <= 32 {
+                buf_size += 32;
+                // Reading ahead can go past the end of the last block
+                let refill = read_u32_be(data, at).unwrap_or(0) as u64;
+                buf |= refill.checked_shl((64 - buf_size) as u32).unwrap_or(0);
+                at += 4;
+            }
+        };
+
+        // Then expand the symbol down to the value
+        while *self.symlen.get(symbol)? != 0 {
+            let (left, right) = self.pair(data, symbol)?;
+            let left_values = *self.symlen.get(left)? as i64 + 1;
+            if offset < left_values {
+                symbol = left;
+            } else {
+                offset -= left_values;
+                symbol = right;
+            }
+        }
+        self.pair(data, symbol).map(|(value, _)| value as i32)
+    }
+}
+
+fn read_u16_le(data: &[u8], at: usize) -> Option<u16> {
+    let bytes = data.get(at..at + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32_le(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u32_be(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64_be(data: &[u8], at: usize) -> Option<u64> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(data.get(at..at + 8)?);
+    Some(u64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn square(name: &str) -> usize {
+        name.parse::<Square>().unwrap().index()
+    }
+
+    #[test]
+    fn king_pairs() {
+        let indices = Indices::new();
+        let mut codes = BTreeSet::new();
+        let mut pairs = 0;
+        for first in (0..64).filter(|sq| file_of(*sq) <= 3 && off_diagonal(*sq) <= 0) {
+            for second in 0..64 {
+                let adjacent = (rank_of(first) as i32 - rank_of(second) as i32).abs() <= 1
+                    && (file_of(first) as i32 - file_of(second) as i32).abs() <= 1;
+                if adjacent || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                    continue;
+                }
+                pairs += 1;
+                codes.insert(indices.map_kk[indices.map_a1d1d4[first]][second]);
+            }
+        }
+        assert_eq!(pairs, 462);
+        assert_eq!(codes, (0..462).collect());
+    }
+
+    #[test]
+    fn triangle() {
+        let indices = Indices::new();
+        let expected = [
+            ("b1", 0),
+            ("c1", 1),
+            ("d1", 2),
+            ("c2", 3),
+            ("d2", 4),
+            ("d3", 5),
+            ("a1", 6),
+            ("b2", 7),
+            ("c3", 8),
+            ("d4", 9),
+        ];
+        for (name, code) in &expected {
+            assert_eq!(indices.map_a1d1d4[square(name)], *code, "{}", name);
+        }
+    }
+
+    #[test]
+    fn binomials() {
+        let indices = Indices::new();
+        assert_eq!(indices.binomial[0][10], 1);
+        assert_eq!(indices.binomial[1][10], 10);
+        assert_eq!(indices.binomial[2][4], 6);
+        assert_eq!(indices.binomial[3][63], 39_711);
+        assert_eq!(indices.binomial[6][63], 67_945_521);
+        assert_eq!(indices.binomial[4][3], 0);
+    }
+
+    #[test]
+    fn pawn_indices() {
+        let indices = Indices::new();
+        let map = |name| indices.map_pawns[square(name)];
+        assert_eq!(
+            (map("a2"), map("h2"), map("a3"), map("b2")),
+            (47, 46, 45, 35)
+        );
+        assert_eq!((map("d7"), map("e7")), (1, 0));
+
+        assert_eq!(indices.lead_pawns_size[1], [6, 6, 6, 6]);
+        assert_eq!(indices.lead_pawns_size[2], [252, 180, 108, 36]);
+        assert_eq!(indices.lead_pawns_size[3], [5201, 2645, 953, 125]);
+        assert_eq!(indices.lead_pawn_idx[2][square("a2")], 0);
+        assert_eq!(indices.lead_pawn_idx[2][square("a3")], 47);
+    }
+
+    #[test]
+    fn material_names() {
+        let material = Material::from_name("KQvK").unwrap();
+        assert_eq!(material.piece_count, 3);
+        assert!(!material.has_pawns && material.has_unique_pieces && !material.symmetric);
+
+        let material = Material::from_name("KRvKR").unwrap();
+        assert!(material.symmetric && material.has_unique_pieces);
+
+        assert!(!Material::from_name("KQQvKRR").unwrap().has_unique_pieces);
+
+        // The side with fewer pawns leads
+        let material = Material::from_name("KPPvKP").unwrap();
+        assert!(material.has_pawns);
+        assert_eq!(material.pawn_count, [1, 2]);
+        assert_eq!(Material::from_name("KRPvK").unwrap().pawn_count, [1, 0]);
+
+        assert!(Material::from_name("KQK").is_none());
+    }
+
+    #[test]
+    fn wdl_order_and_negation() {
+        assert!(Wdl::Loss < Wdl::BlessedLoss);
+        assert!(Wdl::BlessedLoss < Wdl::Draw);
+        assert!(Wdl::Draw < Wdl::CursedWin);
+        assert!(Wdl::CursedWin < Wdl::Win);
+        assert_eq!(-Wdl::Win, Wdl::Loss);
+        assert_eq!(-Wdl::CursedWin, Wdl::BlessedLoss);
+        assert_eq!(-Wdl::Draw, Wdl::Draw);
+        assert_eq!(-Wdl::BlessedLoss, Wdl::CursedWin);
+        assert_eq!(-Wdl::Loss, Wdl::Win);
+    }
+
+    #[test]
+    fn empty_directory() {
+        let dir = std::env::temp_dir().join(format!("tablebase-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tablebase = Tablebase::open(&dir).unwrap();
+        fs::remove_dir(&dir).unwrap();
+        assert_eq!(tablebase.max_pieces(), 0);
+
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4KQ2 w - - 0 1").unwrap();
+        assert!(!tablebase.covers(&position));
+        assert_eq!(tablebase.probe_wdl(&position), None);
+        assert_eq!(tablebase.probe_dtz(&position), None);
+        assert_eq!(tablebase.rank_moves(&position), None);
+
+        assert!(Tablebase::open(dir).is_err());
+    }
+
+    #[test]
+    fn uncovered_positions() {
+        let tablebase = Tablebase::default();
+        let position = Position::starting();
+        assert_eq!(tablebase.probe_wdl(&position), None);
+        assert_eq!(tablebase.rank_moves(&position), None);
+    }
+}
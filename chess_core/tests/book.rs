@@ -0,0 +1,127 @@
+use chess_core::{
+    book::{Book, BookError},
+    Position, Square,
+};
+
+/// A book entry for the position, with the move given like `e2e4` and the promotion code as
+/// Polyglot writes it, 4 for a queen down to 1 for a knight.
+fn entry(position: &Position, uci: &str, promotion: u16, weight: u16) -> Vec<u8> {
+    let square = |name: &str| name.parse::<Square>().unwrap();
+    let (from, to) = (square(&uci[..2]), square(&uci[2..4]));
+    let mv = to.file() as u16
+        | (to.rank() as u16) << 3
+        | (from.file() as u16) << 6
+        | (from.rank() as u16) << 9
+        | promotion << 12;
+
+    let mut bytes = position.zobrist_key().to_be_bytes().to_vec();
+    bytes.extend_from_slice(&mv.to_be_bytes());
+    bytes.extend_from_slice(&weight.to_be_bytes());
+    bytes.extend_from_slice(&[0; 4]);
+    bytes
+}
+
+fn book(entries: &[Vec<u8>]) -> Book {
+    Book::from_bytes(&entries.concat()).unwrap()
+}
+
+/// The book moves as `(uci, weight)`.
+fn moves(book: &Book, position: &Position) -> Vec<(String, u32)> {
+    book.moves(position)
+        .into_iter()
+        .map(|book_move| (book_move.mv.to_string(), book_move.weight))
+        .collect()
+}
+
+fn fen(fen: &str) -> Position {
+    Position::from_fen(fen).unwrap()
+}
+
+#[test]
+fn castling_is_the_king_taking_its_rook() {
+    let position = fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let book = book(&[
+        entry(&position, "e1h1", 0, 10),
+        entry(&position, "e1a1", 0, 5),
+    ]);
+    assert_eq!(
+        moves(&book, &position),
+        [("e1g1".to_string(), 10), ("e1c1".to_string(), 5)]
+    );
+}
+
+#[test]
+fn promotions() {
+    let position = fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    let book = book(&[
+        entry(&position, "b7b8", 4, 4),
+        entry(&position, "b7b8", 3, 3),
+        entry(&position, "b7b8", 2, 2),
+        entry(&position, "b7b8", 1, 1),
+    ]);
+    assert_eq!(
+        moves(&book, &position),
+        [
+            ("b7b8q".to_string(), 4),
+            ("b7b8r".to_string(), 3),
+            ("b7b8b".to_string(), 2),
+            ("b7b8n".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn illegal_entries_are_left_out() {
+    let position = Position::starting();
+    let book = book(&[
+        entry(&position, "e2e5", 0, 100),
+        entry(&position, "e1e2", 0, 100),
+        entry(&position, "d2d4", 0, 1),
+    ]);
+    assert_eq!(moves(&book, &position), [("d2d4".to_string(), 1)]);
+    assert!(moves(&book, &fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")).is_empty());
+}
+
+#[test]
+fn merged_weights_add_up() {
+    let position = Position::starting();
+    let mut merged = book(&[entry(&position, "e2e4", 0, 3)]);
+    merged.merge(book(&[
+        entry(&position, "d2d4", 0, 5),
+        entry(&position, "e2e4", 0, 4),
+    ]));
+    assert_eq!(
+        moves(&merged, &position),
+        [("e2e4".to_string(), 7), ("d2d4".to_string(), 5)]
+    );
+}
+
+#[test]
+fn picks_by_weight() {
+    let position = Position::starting();
+    let book = book(&[
+        entry(&position, "e2e4", 0, 1),
+        entry(&position, "d2d4", 0, 3),
+        entry(&position, "g1f3", 0, 0),
+    ]);
+    let pick = |random| book.pick(&position, random).unwrap().to_string();
+    let picks: Vec<String> = (0..8).map(pick).collect();
+    assert_eq!(
+        picks,
+        ["d2d4", "d2d4", "d2d4", "e2e4", "d2d4", "d2d4", "d2d4", "e2e4"]
+    );
+
+    // Nothing to pick once out of book, or when every move has no weight
+    assert_eq!(book.pick(&fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), 0), None);
+    let unweighted = self::book(&[entry(&position, "e2e4", 0, 0)]);
+    assert_eq!(unweighted.pick(&position, 0), None);
+}
+
+#[test]
+fn rejects_partial_entries() {
+    assert_eq!(
+        Book::from_bytes(&[0; 17]).unwrap_err(),
+        BookError { len: 17 }
+    );
+    assert!(Book::from_bytes(&[]).unwrap().is_empty());
+}
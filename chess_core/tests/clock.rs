@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use chess_core::{
+    clock::{Bonus, Clock, Period, TimeControl, TimeControlError},
+    PieceColor,
+};
+
+fn minutes(minutes: u64) -> Duration {
+    Duration::from_secs(minutes * 60)
+}
+
+fn seconds(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+
+fn clock(control: &str) -> Clock {
+    Clock::new(TimeControl::parse(control).unwrap())
+}
+
+#[test]
+fn parses_time_controls() {
+    assert_eq!(
+        TimeControl::parse("5+3"),
+        Ok(TimeControl {
+            periods: vec![Period {
+                moves: None,
+                time: minutes(5),
+            }],
+            bonus: Bonus::Fischer(seconds(3)),
+        })
+    );
+    assert_eq!(
+        TimeControl::parse("15d10").unwrap().bonus,
+        Bonus::Bronstein(seconds(10))
+    );
+    assert_eq!(
+        TimeControl::parse("0.5").unwrap().periods[0].time,
+        seconds(30)
+    );
+    assert_eq!(
+        TimeControl::parse("40/90,30+30"),
+        Ok(TimeControl {
+            periods: vec![
+                Period {
+                    moves: Some(40),
+                    time: minutes(90),
+                },
+                Period {
+                    moves: None,
+                    time: minutes(30),
+                },
+            ],
+            bonus: Bonus::Fischer(seconds(30)),
+        })
+    );
+    // One period that repeats every 40 moves
+    assert_eq!(
+        TimeControl::parse("40/90+30").unwrap().periods,
+        vec![Period {
+            moves: Some(40),
+            time: minutes(90),
+        }]
+    );
+}
+
+#[test]
+fn rejects_bad_time_controls() {
+    let period = |text: &str| Err(TimeControlError::InvalidPeriod(text.to_string()));
+    let bonus = |text: &str| Err(TimeControlError::InvalidBonus(text.to_string()));
+    assert_eq!(TimeControl::parse("five"), period("five"));
+    assert_eq!(TimeControl::parse("0+3"), period("0"));
+    assert_eq!(TimeControl::parse("-5"), period("-5"));
+    assert_eq!(TimeControl::parse("0/90"), period("0/90"));
+    assert_eq!(TimeControl::parse("1e300"), period("1e300"));
+    assert_eq!(TimeControl::parse("inf"), period("inf"));
+    assert_eq!(TimeControl::parse("NaN"), period("NaN"));
+    assert_eq!(TimeControl::parse("5+x"), bonus("x"));
+    assert_eq!(TimeControl::parse("5+-1"), bonus("-1"));
+    assert_eq!(TimeControl::parse("5+1e300"), bonus("1e300"));
+    assert_eq!(TimeControl::parse("5dNaN"), bonus("NaN"));
+    assert_eq!(
+        TimeControl::parse("30,40/90"),
+        Err(TimeControlError::UnlimitedPeriodNotLast)
+    );
+}
+
+#[test]
+fn fischer_increment() {
+    let mut clock = clock("5+3");
+    clock.tick(PieceColor::White, seconds(2));
+    clock.finish_move(PieceColor::White);
+    assert_eq!(clock.remaining(PieceColor::White), minutes(5) + seconds(1));
+    assert_eq!(clock.remaining(PieceColor::Black), minutes(5));
+}
+
+#[test]
+fn bronstein_delay() {
+    let mut clock = clock("15d10");
+    clock.tick(PieceColor::White, seconds(4));
+    clock.finish_move(PieceColor::White);
+    assert_eq!(clock.remaining(PieceColor::White), minutes(15));
+
+    // Only up to the delay comes back
+    clock.tick(PieceColor::White, seconds(12));
+    clock.finish_move(PieceColor::White);
+    assert_eq!(clock.remaining(PieceColor::White), minutes(15) - seconds(2));
+}
+
+#[test]
+fn next_period() {
+    let mut clock = clock("2/10,5");
+    for _ in 0..2 {
+        clock.tick(PieceColor::Black, minutes(1));
+        clock.finish_move(PieceColor::Black);
+    }
+    assert_eq!(clock.remaining(PieceColor::Black), minutes(13));
+
+    // The last period is for the rest of the game
+    for _ in 0..5 {
+        clock.tick(PieceColor::Black, minutes(1));
+        clock.finish_move(PieceColor::Black);
+    }
+    assert_eq!(clock.remaining(PieceColor::Black), minutes(8));
+}
+
+#[test]
+fn repeating_period() {
+    let mut clock = clock("2/10");
+    for _ in 0..4 {
+        clock.tick(PieceColor::White, minutes(1));
+        clock.finish_move(PieceColor::White);
+    }
+    assert_eq!(clock.remaining(PieceColor::White), minutes(26));
+}
+
+#[test]
+fn flag_fall() {
+    let mut clock = clock("1+5");
+    clock.tick(PieceColor::White, minutes(2));
+    assert!(clock.is_flagged(PieceColor::White));
+    assert!(!clock.is_flagged(PieceColor::Black));
+
+    // No increment once the flag has fallen
+    clock.finish_move(PieceColor::White);
+    assert!(clock.is_flagged(PieceColor::White));
+}
+
+#[test]
+fn huge_times_saturate() {
+    let mut clock = Clock::new(TimeControl {
+        periods: vec![Period {
+            moves: Some(1),
+            time: Duration::MAX,
+        }],
+        bonus: Bonus::Fischer(Duration::MAX),
+    });
+    clock.finish_move(PieceColor::White);
+    assert_eq!(clock.remaining(PieceColor::White), Duration::MAX);
+}
@@ -0,0 +1,112 @@
+use chess_core::{
+    fen::{FenError, STARTING_FEN},
+    Move, Position, Square,
+};
+
+fn square(name: &str) -> Square {
+    name.parse().unwrap()
+}
+
+#[test]
+fn round_trips() {
+    for fen in &[
+        STARTING_FEN,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 17 42",
+    ] {
+        assert_eq!(Position::from_fen(fen).unwrap().to_fen(), *fen);
+    }
+}
+
+#[test]
+fn move_counters_are_optional() {
+    let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+    assert_eq!(position.halfmove_clock(), 0);
+    assert_eq!(position.fullmove_number(), 1);
+}
+
+#[test]
+fn rejects_bad_board() {
+    for board in &[
+        "4k3/8/8/8/8/8/8",
+        "4k3/8/8/8/8/8/8/4K4",
+        "4k3/8/8/8/8/8/8/4K2",
+        "4k3/8/8/8/8/8/8/4X3",
+        "4k3/8/8/8/8/8/8/04K3",
+        "4k3/8/8/8/8/8/8/4K03",
+    ] {
+        let fen = format!("{} w - - 0 1", board);
+        assert_eq!(
+            Position::from_fen(&fen),
+            Err(FenError::InvalidBoard(board.to_string())),
+            "{}",
+            fen
+        );
+    }
+    assert_eq!(
+        Position::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+        Err(FenError::InvalidKings)
+    );
+}
+
+#[test]
+fn rejects_bad_castling() {
+    for castling in &["KQx", "W", "--"] {
+        let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", castling);
+        assert_eq!(
+            Position::from_fen(&fen),
+            Err(FenError::InvalidCastling(castling.to_string()))
+        );
+    }
+}
+
+#[test]
+fn rejects_bad_en_passant() {
+    for (fen, square) in &[
+        // Rank 3 with White to move
+        ("4k3/8/8/8/8/8/3PB3/4K3 w - e3 0 1", "e3"),
+        // Rank 6 with Black to move
+        ("4k3/8/8/4pP2/8/8/8/4K3 b - e6 0 1", "e6"),
+        // No pawn in front of the square
+        ("4k3/8/8/5P2/8/8/8/4K3 w - e6 0 1", "e6"),
+        // A pawn of the side to move in front of it
+        ("4k3/8/8/4PP2/8/8/8/4K3 w - e6 0 1", "e6"),
+        ("4k3/8/8/8/8/8/8/4K3 w - e5 0 1", "e5"),
+        ("4k3/8/8/8/8/8/8/4K3 w - i6 0 1", "i6"),
+    ] {
+        assert_eq!(
+            Position::from_fen(fen),
+            Err(FenError::InvalidEnPassant(square.to_string())),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn en_passant_capture() {
+    let mut position = Position::from_fen("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 2").unwrap();
+    let capture = Move::new(square("f5"), square("e6"));
+    assert!(position.is_legal(capture));
+    position.make_move(capture);
+    assert_eq!(position.to_fen(), "4k3/8/4P3/8/8/8/8/4K3 b - - 0 2");
+}
+
+#[test]
+fn rejects_bad_fields() {
+    assert_eq!(
+        Position::from_fen("4k3/8/8/8/8/8/8/4K3 w"),
+        Err(FenError::WrongFieldCount(2))
+    );
+    assert_eq!(
+        Position::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+        Err(FenError::InvalidSideToMove("x".to_string()))
+    );
+    assert_eq!(
+        Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+        Err(FenError::InvalidMoveCounter("0".to_string()))
+    );
+}
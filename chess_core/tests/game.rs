@@ -0,0 +1,147 @@
+use chess_core::{san::parse_san, Game, GameResult, Outcome, PieceColor, Position, Termination};
+
+fn from_fen(fen: &str) -> Game {
+    Game::new(Position::from_fen(fen).unwrap())
+}
+
+fn play(game: &mut Game, moves: &[&str]) {
+    for san in moves {
+        let mv = parse_san(game.position(), san).unwrap();
+        game.play(mv);
+    }
+}
+
+const KNIGHT_SHUFFLE: [&str; 4] = ["Nf3", "Nf6", "Ng1", "Ng8"];
+
+fn draw(termination: Termination) -> Option<Outcome> {
+    Some(Outcome {
+        result: GameResult::Draw,
+        termination,
+    })
+}
+
+#[test]
+fn threefold_repetition_is_claimable() {
+    let mut game = Game::new(Position::starting());
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(game.repetitions(), 2);
+    assert_eq!(game.claimable_draw(), None);
+
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(game.repetitions(), 3);
+    assert_eq!(
+        game.claimable_draw(),
+        Some(Termination::ThreefoldRepetition)
+    );
+    assert!(!game.is_over());
+
+    assert!(game.claim_draw());
+    assert_eq!(game.outcome(), draw(Termination::ThreefoldRepetition));
+    assert_eq!(game.claimable_draw(), None);
+}
+
+#[test]
+fn fivefold_repetition_ends_the_game() {
+    let mut game = Game::new(Position::starting());
+    for _ in 0..3 {
+        play(&mut game, &KNIGHT_SHUFFLE);
+    }
+    assert_eq!(game.repetitions(), 4);
+    assert!(!game.is_over());
+
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(game.repetitions(), 5);
+    assert_eq!(game.outcome(), draw(Termination::FivefoldRepetition));
+}
+
+#[test]
+fn repetitions_restart_after_pawn_moves_and_captures() {
+    let mut game = Game::new(Position::starting());
+    play(&mut game, &KNIGHT_SHUFFLE);
+    play(&mut game, &["e4", "e5"]);
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(game.repetitions(), 2);
+
+    let mut game = from_fen("3k4/8/8/8/8/8/4K3/n6R w - - 0 1");
+    play(&mut game, &["Rg1", "Kc8", "Rh1", "Kd8"]);
+    assert_eq!(game.repetitions(), 2);
+    play(&mut game, &["Rxa1", "Kc8", "Rb1", "Kd8", "Ra1", "Kc8"]);
+    assert_eq!(game.repetitions(), 2);
+}
+
+#[test]
+fn fifty_and_seventy_five_move_rules() {
+    let mut game = from_fen("k7/8/8/8/8/8/8/R3K3 w - - 99 80");
+    assert_eq!(game.claimable_draw(), None);
+    play(&mut game, &["Ra2"]);
+    assert_eq!(game.claimable_draw(), Some(Termination::FiftyMoveRule));
+    assert!(!game.is_over());
+
+    let mut game = from_fen("k7/8/8/8/8/8/8/R3K3 w - - 149 105");
+    assert!(!game.is_over());
+    play(&mut game, &["Ra2"]);
+    assert_eq!(game.outcome(), draw(Termination::SeventyFiveMoveRule));
+}
+
+#[test]
+fn insufficient_material() {
+    for fen in &[
+        "k7/8/8/8/8/8/8/4K3 w - - 0 1",
+        "k7/8/8/8/8/8/8/4KN2 w - - 0 1",
+        "k7/8/8/8/8/8/8/4KB2 b - - 0 1",
+        // Bishops on dark squares on both sides
+        "k4b2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+    ] {
+        assert_eq!(
+            from_fen(fen).outcome(),
+            draw(Termination::InsufficientMaterial),
+            "{}",
+            fen
+        );
+    }
+
+    for fen in &[
+        "k7/8/8/8/8/8/8/4KR2 w - - 0 1",
+        // Bishops on squares of both colors
+        "kb6/8/8/8/8/8/8/4KB2 w - - 0 1",
+        "k7/8/8/8/8/8/8/3NKN2 w - - 0 1",
+    ] {
+        assert!(!from_fen(fen).is_over(), "{}", fen);
+    }
+}
+
+#[test]
+fn flag_against_insufficient_material() {
+    let mut game = from_fen("k7/8/8/8/8/8/8/3QK3 w - - 0 1");
+    game.flag(PieceColor::Black);
+    assert_eq!(
+        game.outcome(),
+        Some(Outcome {
+            result: GameResult::WhiteWins,
+            termination: Termination::Timeout,
+        })
+    );
+
+    let mut game = from_fen("k7/8/8/8/8/8/8/3QK3 w - - 0 1");
+    game.flag(PieceColor::White);
+    assert_eq!(
+        game.outcome(),
+        draw(Termination::TimeoutVsInsufficientMaterial)
+    );
+}
+
+#[test]
+fn undo_takes_back_the_end() {
+    let mut game = Game::new(Position::starting());
+    play(&mut game, &["e4"]);
+    game.resign(PieceColor::Black);
+    assert!(game.is_over());
+    game.undo();
+    assert_eq!(game.outcome(), None);
+
+    play(&mut game, &["e4"]);
+    game.flag(PieceColor::White);
+    assert!(game.is_over());
+    game.undo();
+    assert!(!game.is_over());
+}
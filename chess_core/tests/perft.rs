@@ -0,0 +1,114 @@
+//! Perft numbers for the usual reference positions, from
+//! https://www.chessprogramming.org/Perft_Results. The deeper counts take a while in debug
+//! builds, so they are ignored by default: `cargo test --release -- --ignored`.
+
+use chess_core::{
+    fen::STARTING_FEN,
+    perft::{divide, perft},
+    Position,
+};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+/// Checks the counts for depth 1, 2, ... against `expected`.
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let position = Position::from_fen(fen).unwrap();
+    for (idx, nodes) in expected.iter().enumerate() {
+        let depth = idx as u32 + 1;
+        assert_eq!(
+            perft(&position, depth),
+            *nodes,
+            "{} at depth {}",
+            fen,
+            depth
+        );
+    }
+}
+
+#[test]
+fn starting_position() {
+    assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281]);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862]);
+}
+
+#[test]
+fn position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2_812, 43_238]);
+}
+
+#[test]
+fn position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9_467]);
+}
+
+#[test]
+fn position_4_mirrored() {
+    assert_perft(POSITION_4_MIRRORED, &[6, 264, 9_467]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1_486, 62_379]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2_079, 89_890]);
+}
+
+#[test]
+#[ignore]
+fn starting_position_deep() {
+    assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281, 4_865_609]);
+}
+
+#[test]
+#[ignore]
+fn kiwipete_deep() {
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862, 4_085_603]);
+}
+
+#[test]
+#[ignore]
+fn position_3_deep() {
+    assert_perft(POSITION_3, &[14, 191, 2_812, 43_238, 674_624, 11_030_083]);
+}
+
+#[test]
+#[ignore]
+fn position_4_deep() {
+    assert_perft(POSITION_4, &[6, 264, 9_467, 422_333]);
+}
+
+#[test]
+#[ignore]
+fn position_5_deep() {
+    assert_perft(POSITION_5, &[44, 1_486, 62_379, 2_103_487]);
+}
+
+#[test]
+#[ignore]
+fn position_6_deep() {
+    assert_perft(POSITION_6, &[46, 2_079, 89_890, 3_894_594]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let position = Position::from_fen(KIWIPETE).unwrap();
+    let divided = divide(&position, 3);
+    assert_eq!(divided.len(), 48);
+    assert_eq!(
+        divided.iter().map(|(_, nodes)| nodes).sum::<u64>(),
+        perft(&position, 3)
+    );
+}
@@ -0,0 +1,137 @@
+use chess_core::{
+    pgn::{read_pgn, write_pgn, PgnError, PgnHeaders},
+    san::{parse_san, to_san},
+    Game, PieceColor, Position,
+};
+
+/// The game's moves in SAN.
+fn san_moves(game: &Game) -> Vec<String> {
+    let mut position = game.starting_position().clone();
+    game.moves()
+        .iter()
+        .map(|mv| {
+            let san = to_san(&position, *mv);
+            position.make_move(*mv);
+            san
+        })
+        .collect()
+}
+
+fn play(game: &mut Game, moves: &[&str]) {
+    for san in moves {
+        let mv = parse_san(game.position(), san).unwrap();
+        game.play(mv);
+    }
+}
+
+const TWO_GAMES: &str = r#"[Event "Casual"]
+[White "Morphy, Paul"]
+[Black "Duke \"Karl\""]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {The most common move, though
+2. f4 is the King's Gambit} d6 $2 (2... Nc6 3. Bb5 (3. Bc4 Bc5) a6) 3. d4 !?
+Bg4 ; a comment to the end of the line
+4.dxe5 1-0
+
+% An escaped line
+[White "Second"]
+[Black "Game"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/8/R3K3 b - - 0 40"]
+
+40... Kd7 41. Ra7+ *
+"#;
+
+#[test]
+fn reads_several_games() {
+    let games = read_pgn(TWO_GAMES).unwrap();
+    assert_eq!(games.len(), 2);
+
+    let first = &games[0];
+    assert_eq!(first.tag("Event"), Some("Casual"));
+    assert_eq!(first.title(), "Morphy, Paul - Duke \"Karl\"");
+    assert_eq!(first.result, "1-0");
+    // Comments, variations and annotations are skipped
+    assert_eq!(
+        san_moves(&first.game),
+        ["e4", "e5", "Nf3", "d6", "d4", "Bg4", "dxe5"]
+    );
+
+    let second = &games[1];
+    assert_eq!(second.title(), "Second - Game");
+    assert_eq!(second.result, "*");
+    assert_eq!(
+        second.game.starting_position().to_fen(),
+        "4k3/8/8/8/8/8/8/R3K3 b - - 0 40"
+    );
+    assert_eq!(san_moves(&second.game), ["Kd7", "Ra7+"]);
+}
+
+#[test]
+fn stray_parentheses_and_percent_signs() {
+    let games = read_pgn("1. e4 ) e5 (2. d4) 2. Nf3 *").unwrap();
+    assert_eq!(san_moves(&games[0].game), ["e4", "e5", "Nf3"]);
+
+    // `%` only escapes a line it starts
+    let games = read_pgn("%1. d4\n1. e4 e5 *").unwrap();
+    assert_eq!(san_moves(&games[0].game), ["e4", "e5"]);
+    let error = read_pgn("1. e4 % e5 *").unwrap_err();
+    assert_eq!(error.message, "`%` is not a valid move");
+}
+
+#[test]
+fn reports_illegal_moves() {
+    let text = "[White \"A\"]\n[Black \"B\"]\n\n1. e4 e5 1-0\n\n\
+                [White \"C\"]\n[Black \"D\"]\n\n1. d4 d5 2. Ke3 Nf6 *\n";
+    assert_eq!(
+        read_pgn(text).unwrap_err(),
+        PgnError {
+            game_number: 2,
+            game_title: "C - D".to_string(),
+            ply: Some(3),
+            message: "illegal move `Ke3`".to_string(),
+        }
+    );
+
+    let error = read_pgn("[FEN \"not a fen\"]\n\n*\n").unwrap_err();
+    assert_eq!((error.game_number, error.ply), (1, None));
+    assert!(read_pgn("1. e4 Zz9 *")
+        .unwrap_err()
+        .to_string()
+        .contains("ply 2"));
+}
+
+#[test]
+fn round_trips() {
+    let headers = PgnHeaders {
+        event: "Club \"Open\"".to_string(),
+        white: "White".to_string(),
+        black: "Black".to_string(),
+        ..Default::default()
+    };
+
+    let mut game = Game::new(Position::starting());
+    play(
+        &mut game,
+        &[
+            "e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O", "f6",
+        ],
+    );
+    game.resign(PieceColor::Black);
+    let games = read_pgn(&write_pgn(&game, &headers)).unwrap();
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].game.moves(), game.moves());
+    assert_eq!(games[0].tag("Event"), Some("Club \"Open\""));
+    assert_eq!(games[0].result, "1-0");
+
+    // Starting elsewhere with Black to move
+    let position = Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 b - - 0 60").unwrap();
+    let mut game = Game::new(position.clone());
+    play(&mut game, &["Kd7", "b8=Q", "Ke6"]);
+    let pgn = write_pgn(&game, &PgnHeaders::default());
+    assert!(pgn.contains("60... Kd7 61. b8=Q Ke6 *"), "{}", pgn);
+    let games = read_pgn(&pgn).unwrap();
+    assert_eq!(*games[0].game.starting_position(), position);
+    assert_eq!(games[0].game.moves(), game.moves());
+}
@@ -0,0 +1,138 @@
+use chess_core::{
+    san::{parse_san, to_lan, to_san, SanError},
+    Move, PieceType, Position, Square,
+};
+
+fn fen(fen: &str) -> Position {
+    Position::from_fen(fen).unwrap()
+}
+
+fn mv(uci: &str) -> Move {
+    let square = |name: &str| name.parse::<Square>().unwrap();
+    let (from, to) = (square(&uci[..2]), square(&uci[2..4]));
+    match uci.get(4..) {
+        Some("q") => Move::with_promotion(from, to, PieceType::Queen),
+        Some("n") => Move::with_promotion(from, to, PieceType::Knight),
+        _ => Move::new(from, to),
+    }
+}
+
+/// Checks the SAN for each move and that it reads back as the same move.
+fn assert_san(position: &Position, expected: &[(&str, &str)]) {
+    for (uci, san) in expected {
+        assert_eq!(to_san(position, mv(uci)), *san);
+        assert_eq!(parse_san(position, san), Ok(mv(uci)), "{}", san);
+    }
+}
+
+#[test]
+fn disambiguation() {
+    // Knights on the same rank
+    assert_san(
+        &fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1"),
+        &[("b1d2", "Nbd2"), ("f1d2", "Nfd2"), ("b1c3", "Nc3")],
+    );
+    // Rooks on the same file
+    assert_san(
+        &fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1"),
+        &[("a1a3", "R1a3"), ("a5a3", "R5a3"), ("a1b1", "Rb1")],
+    );
+    // One queen shares a file with the second and a rank with the third
+    assert_san(
+        &fen("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1"),
+        &[("a1b2", "Qa1b2"), ("a3b2", "Q3b2"), ("c1b2", "Qcb2")],
+    );
+}
+
+#[test]
+fn check_and_mate() {
+    assert_san(
+        &fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"),
+        &[("a1a8", "Ra8+"), ("a1a7", "Ra7")],
+    );
+    assert_san(
+        &fen("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1"),
+        &[("a1a8", "Ra8#")],
+    );
+}
+
+#[test]
+fn promotion() {
+    assert_san(
+        &fen("4k3/1P6/8/8/8/8/8/7K w - - 0 1"),
+        &[("b7b8q", "b8=Q+"), ("b7b8n", "b8=N")],
+    );
+    let position = fen("2n1k3/1P6/8/8/8/8/8/7K w - - 0 1");
+    assert_san(&position, &[("b7c8q", "bxc8=Q+")]);
+    assert_eq!(parse_san(&position, "bxc8Q"), Ok(mv("b7c8q")));
+}
+
+#[test]
+fn castling() {
+    let position = fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_san(&position, &[("e1g1", "O-O"), ("e1c1", "O-O-O")]);
+    assert_eq!(parse_san(&position, "0-0"), Ok(mv("e1g1")));
+    assert_eq!(parse_san(&position, "0-0-0"), Ok(mv("e1c1")));
+
+    let position = fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+    assert_eq!(
+        parse_san(&position, "O-O"),
+        Err(SanError::Illegal("O-O".to_string()))
+    );
+}
+
+#[test]
+fn en_passant() {
+    assert_san(
+        &fen("4k3/8/8/4pP2/8/8/8/4K3 w - e6 0 2"),
+        &[("f5e6", "fxe6"), ("f5f6", "f6")],
+    );
+}
+
+#[test]
+fn rejects_bad_moves() {
+    let position = fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+    assert_eq!(
+        parse_san(&position, "Nd2"),
+        Err(SanError::Ambiguous("Nd2".to_string()))
+    );
+    assert_eq!(
+        parse_san(&position, "Nd3"),
+        Err(SanError::Illegal("Nd3".to_string()))
+    );
+    assert_eq!(
+        parse_san(&position, "e4"),
+        Err(SanError::Illegal("e4".to_string()))
+    );
+    for invalid in &["", "N", "Nz9", "Nb1*d2"] {
+        assert_eq!(
+            parse_san(&position, invalid),
+            Err(SanError::Invalid(invalid.to_string()))
+        );
+    }
+}
+
+#[test]
+fn long_algebraic_and_uci() {
+    let position = fen("4k3/1P6/8/8/8/8/8/1N2KN2 w - - 0 1");
+    assert_eq!(to_lan(&position, mv("b1d2")), "Nb1-d2");
+    for (text, uci) in &[
+        ("Nb1-d2", "b1d2"),
+        ("Nb1d2", "b1d2"),
+        ("b1d2", "b1d2"),
+        ("b7b8q", "b7b8q"),
+        ("b7-b8=N", "b7b8n"),
+        ("Nf1xd2!?", "f1d2"),
+    ] {
+        assert_eq!(parse_san(&position, text), Ok(mv(uci)), "{}", text);
+    }
+}
+
+#[test]
+fn every_move_reads_back() {
+    let position = fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    for legal in position.legal_moves() {
+        assert_eq!(parse_san(&position, &to_san(&position, legal)), Ok(legal));
+        assert_eq!(parse_san(&position, &to_lan(&position, legal)), Ok(legal));
+    }
+}
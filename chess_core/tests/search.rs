@@ -0,0 +1,27 @@
+use chess_core::{san::parse_san, search, Game, Position, SearchLimits};
+
+#[test]
+fn losing_side_repeats() {
+    // Black can only hope to repeat the position
+    let mut game = Game::new(Position::from_fen("7k/8/8/8/8/8/8/1Q5K b - - 0 1").unwrap());
+    for san in &["Kg8", "Kg1", "Kh8", "Kh1"] {
+        let mv = parse_san(game.position(), san).unwrap();
+        game.play(mv);
+    }
+
+    let limits = SearchLimits {
+        depth: 2,
+        history: game.keys().to_vec(),
+        ..Default::default()
+    };
+    let result = search(game.position(), &limits).unwrap();
+    assert_eq!(result.best_move, parse_san(game.position(), "Kg8").unwrap());
+    assert_eq!(result.score, 0);
+
+    // Without the game's history it's just lost
+    let limits = SearchLimits {
+        depth: 2,
+        ..Default::default()
+    };
+    assert!(search(game.position(), &limits).unwrap().score < -500);
+}
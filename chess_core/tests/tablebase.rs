@@ -0,0 +1,72 @@
+//! Probes real Syzygy tables, which are too big to keep in the repository. Point `SYZYGY_PATH`
+//! at a folder with at least KQvK and KRvK and run `cargo test -- --ignored`.
+
+use chess_core::{
+    tablebase::{Tablebase, Wdl},
+    Position,
+};
+
+fn tablebase() -> Option<Tablebase> {
+    let dir = match std::env::var("SYZYGY_PATH") {
+        Ok(dir) => dir,
+        Err(_) => {
+            eprintln!("SYZYGY_PATH isn't set, skipping");
+            return None;
+        }
+    };
+    Some(Tablebase::open(&dir).unwrap())
+}
+
+fn fen(fen: &str) -> Position {
+    Position::from_fen(fen).unwrap()
+}
+
+#[test]
+#[ignore]
+fn wins_and_draws() {
+    let tablebase = match tablebase() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    // Mate in one
+    for fen in &[
+        "k7/8/1K6/8/8/8/8/7Q w - - 0 1",
+        "k7/8/1K6/8/8/8/8/7R w - - 0 1",
+    ] {
+        let position = self::fen(fen);
+        assert_eq!(tablebase.probe_wdl(&position), Some(Wdl::Win), "{}", fen);
+        assert_eq!(tablebase.probe_dtz(&position), Some(1), "{}", fen);
+    }
+
+    let position = fen("k7/8/1K6/8/8/8/8/7Q b - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&position), Some(Wdl::Loss));
+    assert!(tablebase.probe_dtz(&position).unwrap() < 0);
+
+    // Taking the queen leaves only the kings
+    let position = fen("k7/1Q6/8/8/8/8/8/7K b - - 0 1");
+    assert_eq!(tablebase.probe_wdl(&position), Some(Wdl::Draw));
+    assert_eq!(tablebase.probe_dtz(&position), Some(0));
+    assert_eq!(
+        tablebase.probe_wdl(&fen("k7/8/8/8/8/8/8/7K w - - 0 1")),
+        Some(Wdl::Draw)
+    );
+}
+
+#[test]
+#[ignore]
+fn ranks_the_mate_first() {
+    let tablebase = match tablebase() {
+        Some(tablebase) => tablebase,
+        None => return,
+    };
+    let ranked = tablebase
+        .rank_moves(&fen("k7/8/1K6/8/8/8/8/7R w - - 0 1"))
+        .unwrap();
+    assert_eq!(ranked[0].mv.to_string(), "h1h8");
+    assert_eq!(ranked[0].dtz, 1);
+    // Rb1 is stalemate
+    let stalemate = ranked
+        .iter()
+        .find(|ranked_move| ranked_move.mv.to_string() == "h1b1");
+    assert_eq!(stalemate.map(|ranked_move| ranked_move.dtz), Some(0));
+}
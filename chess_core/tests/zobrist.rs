@@ -0,0 +1,73 @@
+//! Zobrist keys for the test positions in the Polyglot book format description, from
+//! http://hgm.nubati.net/book_format.html.
+
+use chess_core::{san, Position};
+
+/// The moves played from the starting position to reach each test position, its FEN and key.
+const POSITIONS: [(&str, &str, u64); 9] = [
+    (
+        "",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        0x463b96181691fc9c,
+    ),
+    (
+        "e4",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        0x823c9b50fd114196,
+    ),
+    (
+        "e4 d5",
+        "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+        0x0756b94461c50fb0,
+    ),
+    (
+        "e4 d5 e5",
+        "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+        0x662fafb965db29d4,
+    ),
+    (
+        "e4 d5 e5 f5",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        0x22a48b5a8e47ff78,
+    ),
+    (
+        "e4 d5 e5 f5 Ke2",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+        0x652a607ca3f242c1,
+    ),
+    (
+        "e4 d5 e5 f5 Ke2 Kf7",
+        "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+        0x00fdd303c946bdd9,
+    ),
+    (
+        "a4 b5 h4 b4 c4",
+        "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+        0x3c8123ea7b067637,
+    ),
+    (
+        "a4 b5 h4 b4 c4 bxc3 Ra3",
+        "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 1 4",
+        0x5c3f9b829b279560,
+    ),
+];
+
+#[test]
+fn polyglot_keys_from_fen() {
+    for (_, fen, key) in POSITIONS.iter() {
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.zobrist_key(), *key, "{}", fen);
+    }
+}
+
+#[test]
+fn polyglot_keys_after_moves() {
+    for (moves, fen, key) in POSITIONS.iter() {
+        let mut position = Position::starting();
+        for text in moves.split_whitespace() {
+            let mv = san::parse_san(&position, text).unwrap();
+            position.make_move(mv);
+        }
+        assert_eq!(position.zobrist_key(), *key, "{}", fen);
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use chess_core::{book::Book, search, tablebase::Tablebase, Game, Move, PieceColor, SearchLimits};
+
+use crate::{
+    board::play_move,
+    promotion::PendingPromotion,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+/// Plays one side with the built-in search.
+pub struct AiPlugin;
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AiSettings>()
+            .init_resource::<Ai>()
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, ai_move.system())
+            .on_state_exit(APP_STATE_STAGE, AppState::Playing, stop_thinking.system());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            "expert" => Some(Difficulty::Expert),
+            _ => None,
+        }
+    }
+
+    /// Easier levels look fewer moves ahead, harder ones get more time to think.
+    pub fn search_limits(self) -> SearchLimits {
+        let (depth, movetime) = match self {
+            Difficulty::Easy => (1, 200),
+            Difficulty::Medium => (3, 500),
+            Difficulty::Hard => (5, 1000),
+            Difficulty::Expert => (chess_core::search::MAX_DEPTH, 3000),
+        };
+        SearchLimits {
+            depth,
+            movetime: Some(Duration::from_millis(movetime)),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct AiSettings {
+    /// The side the AI plays, if any.
+    pub color: Option<PieceColor>,
+    pub difficulty: Difficulty,
+    /// Whether to play moves from the opening book while it has any for the position.
+    pub book: bool,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        AiSettings {
+            color: None,
+            difficulty: Difficulty::Medium,
+            book: true,
+        }
+    }
+}
+
+impl AiSettings {
+    /// Whether the AI moves for the given side, so the player can't.
+    pub fn plays(&self, color: PieceColor) -> bool {
+        self.color == Some(color)
+    }
+}
+
+/// A search running on its own thread so the board keeps rendering while the AI thinks.
+struct Thinking {
+    /// How many moves had been played when the search started.
+    searching_from: usize,
+    stop: Arc<AtomicBool>,
+    best_move: Mutex<Receiver<Option<Move>>>,
+}
+
+pub struct Ai {
+    thinking: Option<Thinking>,
+    /// Picks the book moves, seeded with `--seed` to play the same openings again.
+    rng: SplitMix64,
+}
+
+impl Ai {
+    pub fn with_seed(seed: u64) -> Self {
+        Ai {
+            thinking: None,
+            rng: SplitMix64(seed),
+        }
+    }
+}
+
+impl Default for Ai {
+    /// A different seed every run. Each `RandomState` gets different random keys, so hashing
+    /// nothing with it is enough.
+    fn default() -> Self {
+        Ai::with_seed(RandomState::new().build_hasher().finish())
+    }
+}
+
+/// A small random number generator, good enough for picking book moves. Any seed works,
+/// 0 included.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+fn ai_move(
+    settings: Res<AiSettings>,
+    book: Res<Book>,
+    tablebase: Res<Arc<Tablebase>>,
+    mut ai: ResMut<Ai>,
+    mut game: ResMut<Game>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+) {
+    // Give up on searches for positions that are gone, e.g. after taking moves back, or for
+    // games that ended while it was thinking
+    if let Some(thinking) = &ai.thinking {
+        if thinking.searching_from != game.moves().len() || game.is_over() {
+            thinking.stop.store(true, Ordering::Relaxed);
+            ai.thinking = None;
+        }
+    }
+
+    if !settings.plays(game.position().side_to_move()) {
+        return;
+    }
+
+    let thinking = match &ai.thinking {
+        Some(thinking) => thinking,
+        None => {
+            if game.is_over() {
+                return;
+            }
+            if settings.book {
+                if let Some(book_move) = book.pick(game.position(), ai.rng.next_u64()) {
+                    play_move(&mut game, &mut pending_promotion, book_move);
+                    return;
+                }
+            }
+            let position = game.position().clone();
+            let stop = Arc::new(AtomicBool::new(false));
+            let limits = SearchLimits {
+                stop: Some(stop.clone()),
+                tablebase: Some(Arc::clone(&tablebase)),
+                history: game.keys().to_vec(),
+                ..settings.difficulty.search_limits()
+            };
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let result = search(&position, &limits);
+                let _ = sender.send(result.map(|result| result.best_move));
+            });
+            ai.thinking = Some(Thinking {
+                searching_from: game.moves().len(),
+                stop,
+                best_move: Mutex::new(receiver),
+            });
+            return;
+        }
+    };
+
+    let best_move = match thinking.best_move.lock().unwrap().try_recv() {
+        Ok(best_move) => best_move,
+        Err(TryRecvError::Empty) => return,
+        Err(TryRecvError::Disconnected) => None,
+    };
+    ai.thinking = None;
+    if let Some(best_move) = best_move {
+        play_move(&mut game, &mut pending_promotion, best_move);
+    }
+}
+
+/// Stops searching when the game is left, so a rematch doesn't get a move from the old game.
+fn stop_thinking(mut ai: ResMut<Ai>) {
+    if let Some(thinking) = ai.thinking.take() {
+        thinking.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,230 @@
+//! The chess rules and search as a UCI engine, for chess GUIs and engine matches, e.g.
+//! `cutechess-cli -engine cmd=rust_chess-uci -engine cmd=stockfish -each proto=uci tc=40/60`.
+
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use chess_core::{
+    perft, san,
+    search::{SearchLimits, SearchResult},
+    search_with_progress, Game, PieceColor, Position,
+};
+
+/// Time kept back when deciding how long to think, for the GUI's and the OS's overhead.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+/// How many more moves to budget for when the GUI doesn't say how many are left.
+const EXPECTED_MOVES_LEFT: u32 = 30;
+
+/// A search running on its own thread, so `stop` can be read while it thinks.
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningSearch {
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+fn main() {
+    let mut game = Game::default();
+    let mut running: Option<RunningSearch> = None;
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") => {
+                println!("id name rust_chess");
+                println!("id author Sahil Khanna");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                game = Game::default();
+            }
+            Some("position") => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                match parse_position(words) {
+                    Ok(position) => game = position,
+                    Err(err) => println!("info string {}", err),
+                }
+            }
+            Some("go") => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+                // `go perft <depth>` prints the divide counts like Stockfish does
+                let mut words = words.peekable();
+                if words.peek() == Some(&"perft") {
+                    words.next();
+                    let depth = words
+                        .next()
+                        .and_then(|depth| depth.parse().ok())
+                        .unwrap_or(1);
+                    print_divide(game.position(), depth);
+                    continue;
+                }
+                let (limits, until_stopped) = parse_go(words, game.position().side_to_move());
+                let limits = SearchLimits {
+                    history: game.keys().to_vec(),
+                    ..limits
+                };
+                running = Some(start_search(game.position().clone(), limits, until_stopped));
+            }
+            // The opponent played the move pondered on, which has been searched for long enough
+            Some("stop") | Some("ponderhit") => {
+                if let Some(search) = running.take() {
+                    search.stop();
+                }
+            }
+            Some("quit") => break,
+            // Options and debug mode aren't supported
+            _ => {}
+        }
+    }
+
+    if let Some(search) = running.take() {
+        search.stop();
+    }
+}
+
+/// Parses `position [startpos | fen <fen>] [moves <move>...]`.
+fn parse_position<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Game, String> {
+    let mut game = match words.next() {
+        Some("startpos") => Game::default(),
+        Some("fen") => {
+            let fen: Vec<&str> = words.by_ref().take_while(|word| *word != "moves").collect();
+            let position = Position::from_fen(&fen.join(" "))
+                .map_err(|err| format!("invalid FEN: {}", err))?;
+            return play_moves(Game::new(position), words);
+        }
+        _ => return Err("expected `startpos` or `fen`".to_string()),
+    };
+    if words.next() == Some("moves") {
+        game = play_moves(game, words)?;
+    }
+    Ok(game)
+}
+
+fn play_moves<'a>(mut game: Game, moves: impl Iterator<Item = &'a str>) -> Result<Game, String> {
+    for text in moves {
+        let mv = san::parse_san(game.position(), text).map_err(|err| err.to_string())?;
+        game.play(mv);
+    }
+    Ok(game)
+}
+
+/// Parses the limits from `go`, turning the clock into a time for this move. Also returns
+/// whether it's `go infinite` or `go ponder`, which search until `stop` and only answer then.
+fn parse_go<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+    side_to_move: PieceColor,
+) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut until_stopped = false;
+    let mut time_left = None;
+    let mut increment = 0;
+    let mut moves_to_go = EXPECTED_MOVES_LEFT;
+
+    while let Some(word) = words.next() {
+        let mut value = || words.next().and_then(|value| value.parse::<u64>().ok());
+        match (word, side_to_move) {
+            ("movetime", _) => limits.movetime = value().map(Duration::from_millis),
+            ("depth", _) => limits.depth = value().unwrap_or(1) as u32,
+            ("wtime", PieceColor::White) | ("btime", PieceColor::Black) => time_left = value(),
+            ("winc", PieceColor::White) | ("binc", PieceColor::Black) => {
+                increment = value().unwrap_or(0)
+            }
+            ("movestogo", _) => moves_to_go = value().unwrap_or(1).max(1) as u32,
+            ("infinite", _) | ("ponder", _) => until_stopped = true,
+            _ => {}
+        }
+    }
+    if until_stopped {
+        return (SearchLimits::default(), true);
+    }
+
+    if let (Some(time_left), None) = (time_left, limits.movetime) {
+        let time_left = Duration::from_millis(time_left);
+        let budget = time_left / moves_to_go + Duration::from_millis(increment) / 2;
+        limits.movetime = Some(
+            budget
+                .min(time_left.checked_sub(MOVE_OVERHEAD).unwrap_or_default())
+                .max(Duration::from_millis(1)),
+        );
+    }
+    (limits, false)
+}
+
+/// Searches on another thread. With `until_stopped` the best move waits for `stop`, even when
+/// the search finishes first.
+fn start_search(position: Position, limits: SearchLimits, until_stopped: bool) -> RunningSearch {
+    let stop = Arc::new(AtomicBool::new(false));
+    let limits = SearchLimits {
+        stop: Some(stop.clone()),
+        ..limits
+    };
+    let stopped = stop.clone();
+    let handle = thread::spawn(move || {
+        let started = Instant::now();
+        let result = search_with_progress(&position, &limits, |result| {
+            print_info(result, started.elapsed());
+        });
+        while until_stopped && !stopped.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        match result {
+            Some(result) => println!("bestmove {}", result.best_move),
+            None => println!("bestmove 0000"),
+        }
+    });
+    RunningSearch { stop, handle }
+}
+
+fn print_info(result: &SearchResult, elapsed: Duration) {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let millis = elapsed.as_millis().max(1);
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.nodes as u128 * 1000 / millis,
+        millis,
+        result.best_move
+    );
+}
+
+fn print_divide(position: &Position, depth: u32) {
+    let divided = perft::divide(position, depth);
+    for (mv, nodes) in &divided {
+        println!("{}: {}", mv, nodes);
+    }
+    println!();
+    println!(
+        "Nodes searched: {}",
+        divided.iter().map(|(_, nodes)| nodes).sum::<u64>()
+    );
+}
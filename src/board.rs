@@ -1,24 +1,55 @@
-use bevy::{app::AppExit, prelude::*};
+use bevy::prelude::*;
 use bevy_mod_picking::{Group, PickState, PickableMesh};
+use chess_core::{
+    pgn::{self, PgnHeaders},
+    Game, Move,
+};
 
-use crate::pieces::{Piece, PieceColor, PieceType};
+use crate::{
+    ai::AiSettings,
+    engine::{Engine, EngineSettings},
+    game_over::outcome_text,
+    move_list::MoveEntry,
+    pieces::{Piece, PieceColor},
+    promotion::PendingPromotion,
+    replay::Replay,
+    state::{AppState, APP_STATE_STAGE},
+};
 
 pub struct BoardPlugin;
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app.init_resource::<SelectedSquare>()
             .init_resource::<SelectedPiece>()
-            .init_resource::<PlayerTurn>()
-            .add_startup_system(create_board.system())
-            .add_system(select_square.system())
-            .add_system(color_squares.system());
-    }
-}
-
-struct PlayerTurn(PieceColor);
-impl Default for PlayerTurn {
-    fn default() -> Self {
-        Self(PieceColor::White)
+            .init_resource::<IllegalClick>()
+            .init_resource::<Game>()
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_status_text.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_status_text.system(),
+            )
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Review,
+                create_status_text.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Review,
+                despawn_status_text.system(),
+            )
+            .on_state_exit(APP_STATE_STAGE, AppState::MainMenu, create_board.system())
+            .on_state_enter(APP_STATE_STAGE, AppState::MainMenu, despawn_board.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, select_square.system())
+            .add_system(color_squares.system())
+            .add_system(game_status.system())
+            .add_system(export_fen.system())
+            .add_system(export_pgn.system());
     }
 }
 
@@ -43,76 +74,90 @@ struct SelectedPiece {
     entity: Option<Entity>,
 }
 
+/// How long a square flashes after trying to move there illegally.
+const ILLEGAL_CLICK_SECONDS: f64 = 0.4;
+
+/// The last square the selected piece couldn't move to, and when it was clicked.
+#[derive(Default)]
+struct IllegalClick {
+    entity: Option<Entity>,
+    clicked_at: f64,
+}
+
 fn select_square(
-    commands: &mut Commands,
     pick_state: Res<PickState>,
     mouse_button_inputs: Res<Input<MouseButton>>,
     mut selected_square: ResMut<SelectedSquare>,
     mut selected_piece: ResMut<SelectedPiece>,
-    mut turn: ResMut<PlayerTurn>,
-    mut app_exit_events: ResMut<Events<AppExit>>,
+    mut game: ResMut<Game>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    mut illegal_click: ResMut<IllegalClick>,
+    time: Res<Time>,
+    engine: Res<Engine>,
+    engine_settings: Res<EngineSettings>,
+    ai_settings: Res<AiSettings>,
     squares_query: Query<&Square>,
-    mut pieces_query: Query<(Entity, &mut Piece)>,
+    pieces_query: Query<(Entity, &Piece)>,
 ) {
     if !mouse_button_inputs.just_pressed(MouseButton::Left) {
         return;
     }
 
+    // Wait for the player to pick what their pawn promotes to or for the computer to move
+    let side_to_move = game.position().side_to_move();
+    if pending_promotion.mv.is_some()
+        || game.is_over()
+        || engine.plays(&engine_settings, side_to_move)
+        || ai_settings.plays(side_to_move)
+    {
+        return;
+    }
+
     if let Some((square_entity, _intersection)) = pick_state.top(Group::default()) {
         if let Ok(square) = squares_query.get(*square_entity) {
-            selected_square.entity = Some(*square_entity);
-
-            if let Some(selected_piece_entity) = selected_piece.entity {
-                let possible_enemy_pieces_vec: Vec<(Entity, Piece)> = pieces_query
-                    .iter_mut()
-                    .map(|(entity, piece)| (entity, *piece))
-                    .collect();
-                let pieces_vec = pieces_query.iter_mut().map(|(_, piece)| *piece).collect();
-
-                // If you do find a selected piece, then update it's position.
-                if let Ok((_piece_entity, mut piece)) = pieces_query.get_mut(selected_piece_entity)
-                {
-                    if piece.is_move_valid((square.x, square.y), pieces_vec) {
-                        for (entity, other_piece) in possible_enemy_pieces_vec {
-                            // If we find a piece on the target that is of the opposite color, despawn
-                            // it.
-                            if other_piece.x == square.x
-                                && other_piece.y == square.y
-                                && other_piece.color != piece.color
-                            {
-                                // If the king is taken, we should exit
-                                if other_piece.piece_type == PieceType::King {
-                                    println!(
-                                        "{} won! Thanks for playing!",
-                                        match turn.0 {
-                                            PieceColor::White => "White",
-                                            PieceColor::Black => "Black",
-                                        }
-                                    );
-                                    app_exit_events.send(AppExit);
-                                }
-                                commands.despawn_recursive(entity);
-                            }
-                        }
-                        // Move piece
-                        piece.x = square.x;
-                        piece.y = square.y;
-
-                        turn.0 = match turn.0 {
-                            PieceColor::White => PieceColor::Black,
-                            PieceColor::Black => PieceColor::White,
-                        }
+            // The player's own piece on the clicked square, if there is one
+            let own_piece = pieces_query
+                .iter()
+                .find(|(_, piece)| {
+                    piece.x == square.x && piece.y == square.y && piece.color == side_to_move
+                })
+                .map(|(piece_entity, _)| piece_entity);
+
+            match selected_piece
+                .entity
+                .and_then(|entity| pieces_query.get(entity).ok())
+            {
+                // If you do find a selected piece, then try to move it there.
+                Some((selected_entity, piece)) => {
+                    let from = chess_core::Square::from_rank_file(piece.x, piece.y);
+                    let to = chess_core::Square::from_rank_file(square.x, square.y);
+                    if from == to {
+                        // Clicking the selected piece again puts it back down
+                        selected_square.entity = None;
+                        selected_piece.entity = None;
+                    } else if game
+                        .position()
+                        .legal_moves_from(from)
+                        .iter()
+                        .any(|mv| mv.to == to)
+                    {
+                        play_move(&mut game, &mut pending_promotion, Move::new(from, to));
+                        selected_square.entity = None;
+                        selected_piece.entity = None;
+                    } else if own_piece.is_some() && own_piece != Some(selected_entity) {
+                        // Switch to another of the player's pieces
+                        selected_square.entity = Some(*square_entity);
+                        selected_piece.entity = own_piece;
+                    } else {
+                        // Keep the piece selected and flash the square it can't go to
+                        illegal_click.entity = Some(*square_entity);
+                        illegal_click.clicked_at = time.seconds_since_startup();
                     }
                 }
-                selected_square.entity = None;
-                selected_piece.entity = None;
-            } else {
                 // If there is no piece previously selected, select the current one.
-                for (piece_entity, piece) in pieces_query.iter_mut() {
-                    if piece.x == square.x && piece.y == square.y && piece.color == turn.0 {
-                        selected_piece.entity = Some(piece_entity);
-                        break;
-                    }
+                None => {
+                    selected_square.entity = own_piece.map(|_| *square_entity);
+                    selected_piece.entity = own_piece;
                 }
             }
         } else {
@@ -123,11 +168,175 @@ fn select_square(
     }
 }
 
+/// Plays a move if it's legal. A pawn move to the last row without a piece to promote to waits
+/// for the player to pick one in the promotion chooser. Returns whether the move was legal.
+pub fn play_move(game: &mut Game, pending_promotion: &mut PendingPromotion, mv: Move) -> bool {
+    let moves: Vec<Move> = game
+        .position()
+        .legal_moves_from(mv.from)
+        .into_iter()
+        .filter(|legal| legal.to == mv.to)
+        .collect();
+
+    if mv.promotion.is_none() && moves.iter().any(|legal| legal.promotion.is_some()) {
+        // A pawn reaching the last row has to be promoted, the chooser finishes the move once
+        // the player picks a piece.
+        pending_promotion.mv = Some(mv);
+        true
+    } else if let Some(legal) = moves.iter().find(|legal| legal.promotion == mv.promotion) {
+        game.play(*legal);
+        true
+    } else {
+        false
+    }
+}
+
+/// Text at the top of the screen showing whose turn it is and whether they are in check.
+struct StatusText;
+
+fn create_status_text(commands: &mut Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: "White to move".to_string(),
+                font: asset_server.load("fonts/DejaVuSans.ttf"),
+                style: TextStyle {
+                    font_size: 40.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(StatusText);
+}
+
+fn despawn_status_text(commands: &mut Commands, query: Query<Entity, With<StatusText>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// Looks for check and the end of the game whenever a move is played. Replays show where in
+/// the game they are instead.
+fn game_status(
+    game: ChangedRes<Game>,
+    state: Res<State<AppState>>,
+    replay: Res<Replay>,
+    mut status_query: Query<&mut Text, With<StatusText>>,
+) {
+    if *state.current() == AppState::Review {
+        for mut text in status_query.iter_mut() {
+            text.value = replay.description();
+        }
+        return;
+    }
+
+    let player = match game.position().side_to_move() {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    };
+
+    let mut status = match game.outcome() {
+        Some(outcome) => outcome_text(outcome),
+        None if game.position().is_check() => format!("{} is in check", player),
+        None => format!("{} to move", player),
+    };
+    if let Some(termination) = game.claimable_draw() {
+        status.push_str(&format!(
+            ", draw by {} can be claimed",
+            termination.description()
+        ));
+    }
+
+    for mut text in status_query.iter_mut() {
+        text.value = status.clone();
+    }
+}
+
+/// File the current position is saved to when exporting it as FEN.
+const FEN_EXPORT_PATH: &str = "position.fen";
+
+/// Pressing F prints the current position as FEN and saves it to a file.
+fn export_fen(keyboard_input: Res<Input<KeyCode>>, move_entry: Res<MoveEntry>, game: Res<Game>) {
+    // F is also a file when typing in a move
+    if !keyboard_input.just_pressed(KeyCode::F) || move_entry.is_active() {
+        return;
+    }
+
+    let fen = game.position().to_fen();
+    println!("{}", fen);
+    match std::fs::write(FEN_EXPORT_PATH, format!("{}\n", fen)) {
+        Ok(()) => println!("Saved position to {}", FEN_EXPORT_PATH),
+        Err(err) => eprintln!("Couldn't save position to {}: {}", FEN_EXPORT_PATH, err),
+    }
+}
+
+/// File the game is saved to as PGN.
+const PGN_EXPORT_PATH: &str = "game.pgn";
+
+/// Pressing S saves the game so far as PGN.
+fn export_pgn(keyboard_input: Res<Input<KeyCode>>, move_entry: Res<MoveEntry>, game: Res<Game>) {
+    if keyboard_input.just_pressed(KeyCode::S) && !move_entry.is_active() {
+        save_pgn(&game);
+    }
+}
+
+pub fn save_pgn(game: &Game) {
+    let headers = PgnHeaders {
+        event: "Casual game".to_string(),
+        site: "rust_chess".to_string(),
+        date: today(),
+        round: "-".to_string(),
+        white: "White".to_string(),
+        black: "Black".to_string(),
+    };
+    match std::fs::write(PGN_EXPORT_PATH, pgn::write_pgn(game, &headers)) {
+        Ok(()) => println!("Saved game to {}", PGN_EXPORT_PATH),
+        Err(err) => eprintln!("Couldn't save game to {}: {}", PGN_EXPORT_PATH, err),
+    }
+}
+
+/// Today's date in PGN's `YYYY.MM.DD` format.
+fn today() -> String {
+    let days = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(since_epoch) => (since_epoch.as_secs() / 86_400) as i64,
+        Err(_) => return "????.??.??".to_string(),
+    };
+
+    // Days since 1970-01-01 to a civil date, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
 fn color_squares(
     pick_state: Res<PickState>,
     selected_square: Res<SelectedSquare>,
+    selected_piece: Res<SelectedPiece>,
+    illegal_click: Res<IllegalClick>,
+    time: Res<Time>,
+    game: Res<Game>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     query: Query<(Entity, &Square, &Handle<StandardMaterial>)>,
+    pieces_query: Query<&Piece>,
 ) {
     // Get entity under cursor
     let top_entity = if let Some((entity, _intersection)) = pick_state.top(Group::default()) {
@@ -136,14 +345,65 @@ fn color_squares(
         None
     };
 
+    // Where the selected piece can go
+    let destinations: Vec<Move> = match selected_piece
+        .entity
+        .and_then(|entity| pieces_query.get(entity).ok())
+    {
+        Some(piece) => game
+            .position()
+            .legal_moves_from(chess_core::Square::from_rank_file(piece.x, piece.y)),
+        None => Vec::new(),
+    };
+
+    // The squares the last move came from and went to, and the king in check
+    let last_move = game.moves().last();
+    let checked_king = if game.position().is_check() {
+        game.position().king_square(game.position().side_to_move())
+    } else {
+        None
+    };
+
+    let illegal_entity = illegal_click.entity.filter(|_| {
+        time.seconds_since_startup() - illegal_click.clicked_at < ILLEGAL_CLICK_SECONDS
+    });
+
     for (entity, square, material_handle) in query.iter() {
         let material = materials.get_mut(material_handle).unwrap();
+        let square_index = chess_core::Square::from_rank_file(square.x, square.y);
+        let destination = destinations.iter().find(|mv| mv.to == square_index);
 
         // Change material color
-        material.albedo = if Some(entity) == top_entity {
+        material.albedo = if Some(entity) == illegal_entity {
+            Color::rgb(1.0, 0.0, 0.0)
+        } else if Some(entity) == top_entity {
             Color::rgb(0.8, 0.3, 0.8)
         } else if Some(entity) == selected_square.entity {
             Color::rgb(0.9, 0.1, 0.1)
+        } else if let Some(mv) = destination {
+            // Captures stand out from quiet moves, including en passant onto an empty square
+            let is_capture = game.position().piece_at(mv.to).is_some()
+                || (game.position().en_passant() == Some(mv.to)
+                    && game
+                        .position()
+                        .piece_at(mv.from)
+                        .map(|piece| piece.piece_type)
+                        == Some(chess_core::PieceType::Pawn));
+            if is_capture {
+                Color::rgb(0.9, 0.5, 0.1)
+            } else {
+                Color::rgb(0.3, 0.7, 0.3)
+            }
+        } else if Some(square_index) == checked_king {
+            // Pulse so the king in check glows
+            let glow = 0.8 + 0.2 * (time.seconds_since_startup() * 4.0).sin() as f32;
+            Color::rgb(glow, 0.25 * glow, 0.15 * glow)
+        } else if last_move.map_or(false, |mv| mv.from == square_index || mv.to == square_index) {
+            if square.is_white() {
+                Color::rgb(0.95, 0.9, 0.5)
+            } else {
+                Color::rgb(0.55, 0.5, 0.1)
+            }
         } else if square.is_white() {
             Color::rgb(1.0, 0.9, 0.9)
         } else {
@@ -179,3 +439,9 @@ fn create_board(
         }
     }
 }
+
+fn despawn_board(commands: &mut Commands, query: Query<Entity, With<Square>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
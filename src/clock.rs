@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use chess_core::{
+    clock::{Clock, TimeControl},
+    Game, Move,
+};
+
+use crate::{
+    pieces::PieceColor,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+/// Chess clocks for both sides, when the game is played with a time control. They only run while
+/// playing, so they stop when the game is over.
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<GameClock>()
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_clock_text.system(),
+            )
+            .on_state_enter(APP_STATE_STAGE, AppState::Playing, start_clock.system())
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_clock_text.system(),
+            )
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, run_clock.system())
+            .add_system(update_clock_text.system());
+    }
+}
+
+#[derive(Default)]
+pub struct GameClock {
+    /// The time control from `--time`, if the game is timed.
+    pub control: Option<TimeControl>,
+    clock: Option<Clock>,
+    /// The moves the players have had their bonus for. Taking moves back doesn't give back time,
+    /// and redoing them doesn't earn the bonus twice.
+    credited: Vec<Move>,
+}
+
+/// Text below the status text showing both players' time.
+struct ClockText;
+
+fn create_clock_text(commands: &mut Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(60.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load("fonts/DejaVuSans.ttf"),
+                style: TextStyle {
+                    font_size: 40.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(ClockText);
+}
+
+fn despawn_clock_text(commands: &mut Commands, query: Query<Entity, With<ClockText>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// Every game, rematches included, starts with full clocks.
+fn start_clock(mut game_clock: ResMut<GameClock>, game: Res<Game>) {
+    game_clock.clock = game_clock.control.clone().map(Clock::new);
+    game_clock.credited = game.moves().to_vec();
+}
+
+/// Runs the clock of the side to move, and ends the game if their time runs out.
+fn run_clock(time: Res<Time>, mut game_clock: ResMut<GameClock>, mut game: ResMut<Game>) {
+    let game_clock = &mut *game_clock;
+    let clock = match &mut game_clock.clock {
+        Some(clock) => clock,
+        None => return,
+    };
+
+    // Each new move gets its own bonus, even if several came in the same frame
+    let moves = game.moves();
+    let redone = game_clock
+        .credited
+        .iter()
+        .zip(moves)
+        .take_while(|(credited, mv)| credited == mv)
+        .count();
+    if redone < moves.len() {
+        let first_to_move = game.starting_position().side_to_move();
+        for ply in redone..moves.len() {
+            let mover = if ply % 2 == 0 {
+                first_to_move
+            } else {
+                first_to_move.opposite()
+            };
+            clock.finish_move(mover);
+        }
+        game_clock.credited = moves.to_vec();
+    }
+
+    if game.is_over() {
+        return;
+    }
+    let side_to_move = game.position().side_to_move();
+    clock.tick(side_to_move, time.delta());
+    if clock.is_flagged(side_to_move) {
+        game.flag(side_to_move);
+    }
+}
+
+fn update_clock_text(game_clock: Res<GameClock>, mut query: Query<&mut Text, With<ClockText>>) {
+    let value = match &game_clock.clock {
+        Some(clock) => format!(
+            "White {}\nBlack {}",
+            format_time(clock, PieceColor::White),
+            format_time(clock, PieceColor::Black)
+        ),
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+/// Minutes and seconds like `4:05`, with tenths in the last ten seconds.
+fn format_time(clock: &Clock, color: PieceColor) -> String {
+    let remaining = clock.remaining(color);
+    let seconds = remaining.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, remaining.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
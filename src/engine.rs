@@ -0,0 +1,319 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use chess_core::{san, Game, PieceColor, Position};
+
+use crate::{
+    board::play_move,
+    promotion::PendingPromotion,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+/// Plays one side with an external engine speaking the Universal Chess Interface.
+pub struct EnginePlugin;
+impl Plugin for EnginePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<EngineSettings>()
+            .init_resource::<Engine>()
+            .on_state_enter(APP_STATE_STAGE, AppState::Playing, new_engine_game.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, engine_move.system())
+            .on_state_exit(APP_STATE_STAGE, AppState::Playing, stop_engine.system());
+    }
+}
+
+/// How long the engine gets to think about each move.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchLimit {
+    /// Milliseconds per move.
+    MoveTime(u32),
+    /// Plies to search.
+    Depth(u32),
+}
+
+pub struct EngineSettings {
+    /// The engine executable, no engine is used without one.
+    pub path: Option<PathBuf>,
+    pub limit: SearchLimit,
+    /// The side the engine plays.
+    pub color: PieceColor,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            path: None,
+            limit: SearchLimit::MoveTime(1000),
+            color: PieceColor::Black,
+        }
+    }
+}
+
+/// How long to wait for the engine to answer `uci` and `isready`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the engine gets to quit before it's killed.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A running engine process.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines the engine wrote, read on another thread so the game never waits on the engine.
+    lines: Mutex<Receiver<String>>,
+    /// Searches that were stopped and whose `bestmove` hasn't come yet.
+    stopped_searches: usize,
+}
+
+impl UciEngine {
+    /// Starts the engine and waits for it to be ready for a new game.
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("engine stdin is piped");
+        let stdout = child.stdout.take().expect("engine stdout is piped");
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            child,
+            stdin,
+            lines: Mutex::new(receiver),
+            stopped_searches: 0,
+        };
+        engine.send("uci")?;
+        engine.wait_for("uciok")?;
+        engine.send("ucinewgame")?;
+        engine.send("isready")?;
+        engine.wait_for("readyok")?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn wait_for(&self, expected: &str) -> io::Result<()> {
+        let lines = self.lines.lock().unwrap();
+        loop {
+            match lines.recv_timeout(HANDSHAKE_TIMEOUT) {
+                Ok(line) if line.trim() == expected => return Ok(()),
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("engine didn't answer with `{}`", expected),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "engine quit during startup",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Starts searching the game's current position.
+    pub fn go(&mut self, game: &Game, limit: SearchLimit) -> io::Result<()> {
+        let mut position = if *game.starting_position() == Position::starting() {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", game.starting_position().to_fen())
+        };
+        if !game.moves().is_empty() {
+            position.push_str(" moves");
+            for mv in game.moves() {
+                position.push_str(&format!(" {}", mv));
+            }
+        }
+        self.send(&position)?;
+        match limit {
+            SearchLimit::MoveTime(ms) => self.send(&format!("go movetime {}", ms)),
+            SearchLimit::Depth(depth) => self.send(&format!("go depth {}", depth)),
+        }
+    }
+
+    /// Stops the search. The move it answers with is left out of `best_move`.
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.stopped_searches += 1;
+        self.send("stop")
+    }
+
+    /// Lets the engine know the next search is from a different game.
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.send("isready")
+    }
+
+    /// The move from the engine's `bestmove` line, once it has finished searching.
+    pub fn best_move(&mut self) -> io::Result<Option<String>> {
+        let lines = self.lines.lock().unwrap();
+        loop {
+            match lines.try_recv() {
+                Ok(line) => {
+                    let mut words = line.split_whitespace();
+                    if words.next() != Some("bestmove") {
+                        continue;
+                    }
+                    if self.stopped_searches > 0 {
+                        self.stopped_searches -= 1;
+                    } else {
+                        return Ok(words.next().map(|mv| mv.to_string()));
+                    }
+                }
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "engine quit unexpectedly",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    /// Asks the engine to quit, and kills it if it hasn't within `QUIT_TIMEOUT`.
+    fn drop(&mut self) {
+        if self.send("quit").is_ok() {
+            let deadline = Instant::now() + QUIT_TIMEOUT;
+            while Instant::now() < deadline {
+                match self.child.try_wait() {
+                    Ok(Some(_)) | Err(_) => return,
+                    Ok(None) => thread::sleep(Duration::from_millis(10)),
+                }
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The engine playing against the player, if there is one.
+#[derive(Default)]
+pub struct Engine {
+    pub uci: Option<UciEngine>,
+    /// The position the engine is searching, if it is.
+    searching: Option<Position>,
+}
+
+impl Engine {
+    pub fn new(uci: UciEngine) -> Self {
+        Engine {
+            uci: Some(uci),
+            searching: None,
+        }
+    }
+
+    /// Whether the engine moves for the given side, so the player can't.
+    pub fn plays(&self, settings: &EngineSettings, color: PieceColor) -> bool {
+        self.uci.is_some() && settings.color == color
+    }
+
+    /// Stops the engine's search, if it's searching.
+    fn stop(&mut self) {
+        if self.searching.take().is_none() {
+            return;
+        }
+        if let Some(uci) = &mut self.uci {
+            if let Err(err) = uci.stop() {
+                eprintln!("Lost the engine: {}", err);
+                self.uci = None;
+            }
+        }
+    }
+}
+
+/// Every game, rematches included, is a new game for the engine.
+fn new_engine_game(mut engine: ResMut<Engine>) {
+    if let Some(uci) = &mut engine.uci {
+        if let Err(err) = uci.new_game() {
+            eprintln!("Lost the engine: {}", err);
+            engine.uci = None;
+        }
+    }
+}
+
+/// The engine doesn't keep thinking once the game is over or left.
+fn stop_engine(mut engine: ResMut<Engine>) {
+    engine.stop();
+}
+
+fn engine_move(
+    settings: Res<EngineSettings>,
+    mut engine: ResMut<Engine>,
+    mut game: ResMut<Game>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+) {
+    let engine = &mut *engine;
+
+    // The game moved on while the engine was thinking, e.g. moves were taken back
+    if engine
+        .searching
+        .as_ref()
+        .map_or(false, |searching| searching != game.position())
+    {
+        engine.stop();
+    }
+
+    if !engine.plays(&settings, game.position().side_to_move()) || game.is_over() {
+        return;
+    }
+
+    let uci = engine.uci.as_mut().unwrap();
+    if engine.searching.is_none() {
+        if let Err(err) = uci.go(&game, settings.limit) {
+            eprintln!("Couldn't send the position to the engine: {}", err);
+            engine.uci = None;
+            return;
+        }
+        engine.searching = Some(game.position().clone());
+        return;
+    }
+
+    let best_move = match uci.best_move() {
+        Ok(Some(best_move)) => best_move,
+        Ok(None) => return,
+        Err(err) => {
+            eprintln!("Lost the engine: {}", err);
+            engine.uci = None;
+            return;
+        }
+    };
+    engine.searching = None;
+
+    let legal = san::parse_san(game.position(), &best_move)
+        .map(|mv| play_move(&mut game, &mut pending_promotion, mv))
+        .unwrap_or(false);
+    if !legal {
+        eprintln!("The engine played an illegal move: {}", best_move);
+        engine.uci = None;
+    }
+}
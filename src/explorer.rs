@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bevy::prelude::*;
+use chess_core::{
+    book::Book,
+    san,
+    tablebase::{Tablebase, Wdl},
+    Game, PieceColor, Position,
+};
+
+use crate::state::{AppState, APP_STATE_STAGE};
+
+/// A panel listing the opening book's moves for the position on the board, or what the
+/// tablebases say about it once few enough pieces are left.
+pub struct ExplorerPlugin;
+impl Plugin for ExplorerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TablebaseProbes>()
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_explorer_text.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_explorer_text.system(),
+            )
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Review,
+                create_explorer_text.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Review,
+                despawn_explorer_text.system(),
+            )
+            .add_system(update_explorer.system())
+            .add_system(receive_tablebase_result.system());
+    }
+}
+
+/// Text on the left below the clocks.
+struct ExplorerText;
+
+/// What the tablebases say about positions by Zobrist key, `None` when they don't have it.
+/// Opening a table and probing DTZ can take a while, so probes run on their own thread.
+#[derive(Default)]
+struct TablebaseProbes {
+    results: HashMap<u64, Option<String>>,
+    probing: Option<Probing>,
+}
+
+struct Probing {
+    key: u64,
+    result: Mutex<Receiver<Option<String>>>,
+}
+
+fn create_explorer_text(commands: &mut Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(160.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: asset_server.load("fonts/DejaVuSans.ttf"),
+                style: TextStyle {
+                    font_size: 30.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(ExplorerText);
+}
+
+fn despawn_explorer_text(commands: &mut Commands, query: Query<Entity, With<ExplorerText>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// Shows the tablebase result if it's known, otherwise the book moves while the tablebases
+/// are probed for positions they could have.
+fn update_explorer(
+    game: ChangedRes<Game>,
+    book: Res<Book>,
+    tablebase: Res<Arc<Tablebase>>,
+    mut probes: ResMut<TablebaseProbes>,
+    mut text_query: Query<&mut Text, With<ExplorerText>>,
+) {
+    let position = game.position();
+    let key = position.zobrist_key();
+    let value = match probes.results.get(&key) {
+        Some(Some(result)) => format!("Tablebase\n{}", result),
+        Some(None) => book_text(&book, position),
+        None => {
+            let probing = matches!(&probes.probing, Some(probing) if probing.key == key);
+            if !probing && tablebase.covers(position) {
+                // Replaces any probe of a position that's gone
+                let tablebase = Arc::clone(&tablebase);
+                let position = position.clone();
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = sender.send(tablebase_result(&tablebase, &position));
+                });
+                probes.probing = Some(Probing {
+                    key,
+                    result: Mutex::new(receiver),
+                });
+            }
+            book_text(&book, position)
+        }
+    };
+
+    for mut text in text_query.iter_mut() {
+        text.value = value.clone();
+    }
+}
+
+/// Shows the tablebase result once the probe is done, if the position is still on the board.
+fn receive_tablebase_result(
+    game: Res<Game>,
+    mut probes: ResMut<TablebaseProbes>,
+    mut text_query: Query<&mut Text, With<ExplorerText>>,
+) {
+    let (key, result) = match &probes.probing {
+        Some(probing) => match probing.result.lock().unwrap().try_recv() {
+            Ok(result) => (probing.key, result),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => (probing.key, None),
+        },
+        None => return,
+    };
+    probes.probing = None;
+    probes.results.insert(key, result.clone());
+
+    if let Some(result) = result {
+        if game.position().zobrist_key() == key {
+            for mut text in text_query.iter_mut() {
+                text.value = format!("Tablebase\n{}", result);
+            }
+        }
+    }
+}
+
+/// Each book move with its weight and share of the total, e.g. `e4  120 (45%)`. Empty when
+/// there are no books to look in.
+fn book_text(book: &Book, position: &Position) -> String {
+    if book.is_empty() {
+        String::new()
+    } else {
+        let moves = book.moves(position);
+        let total: u32 = moves.iter().map(|book_move| book_move.weight).sum();
+        if moves.is_empty() {
+            "Out of book".to_string()
+        } else {
+            let mut value = "Book moves".to_string();
+            for book_move in moves {
+                let share = if total > 0 {
+                    book_move.weight * 100 / total
+                } else {
+                    0
+                };
+                value.push_str(&format!(
+                    "\n{}  {} ({}%)",
+                    san::to_san(position, book_move.mv),
+                    book_move.weight,
+                    share
+                ));
+            }
+            value
+        }
+    }
+}
+
+/// The result with best play, e.g. "White wins, DTZ 23" where DTZ is the plies until the next
+/// capture or pawn move. `None` without tables for the position.
+fn tablebase_result(tablebase: &Tablebase, position: &Position) -> Option<String> {
+    let wdl = tablebase.probe_wdl(position)?;
+    let dtz = tablebase.probe_dtz(position)?;
+
+    let side_to_move = position.side_to_move();
+    let winner = if wdl > Wdl::Draw {
+        side_to_move
+    } else {
+        side_to_move.opposite()
+    };
+    let winner = match winner {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    };
+    Some(match wdl {
+        Wdl::Win | Wdl::Loss => format!("{} wins, DTZ {}", winner, dtz.abs()),
+        Wdl::CursedWin | Wdl::BlessedLoss => format!(
+            "{} wins, DTZ {}, but it's\ndrawn by the fifty-move rule",
+            winner,
+            dtz.abs()
+        ),
+        Wdl::Draw => "Draw".to_string(),
+    })
+}
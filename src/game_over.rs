@@ -0,0 +1,368 @@
+use bevy::{app::AppExit, prelude::*};
+use chess_core::{Game, GameResult, Outcome};
+
+use crate::{
+    ai::AiSettings,
+    board::save_pgn,
+    engine::{Engine, EngineSettings},
+    promotion::PendingPromotion,
+    replay::Replay,
+    state::{change_state, AppState, APP_STATE_STAGE},
+};
+
+/// Resigning, and the screen over the board once a game has ended with what to do next.
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.on_state_update(APP_STATE_STAGE, AppState::Playing, resign_button.system())
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_resign_button.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_resign_button.system(),
+            )
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, show_claim_draw.system())
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                claim_draw_button.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_claim_draw_button.system(),
+            )
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, end_game.system())
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                game_over_screen.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                game_over_buttons.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::GameOver,
+                despawn_game_over_screen.system(),
+            );
+    }
+}
+
+/// What the status text and the game over screen say about how the game ended, e.g.
+/// "White wins by checkmate".
+pub fn outcome_text(outcome: Outcome) -> String {
+    let reason = outcome.termination.description();
+    match outcome.result {
+        GameResult::WhiteWins => format!("White wins by {}", reason),
+        GameResult::BlackWins => format!("Black wins by {}", reason),
+        GameResult::Draw => format!("Draw by {}", reason),
+        GameResult::Ongoing => "Game over".to_string(),
+    }
+}
+
+struct ResignButton;
+
+/// Only there while a draw can be claimed.
+struct ClaimDrawButton;
+
+/// Root node of the game over screen.
+struct GameOverScreen;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GameOverAction {
+    /// The same starting position again, with the computer playing the other side.
+    Rematch,
+    /// Back to the main menu to start another game.
+    NewGame,
+    /// Step through the game that just ended.
+    Review,
+    SavePgn,
+    Quit,
+}
+
+struct GameOverButton(GameOverAction);
+
+const GAME_OVER_CHOICES: [(GameOverAction, &str); 5] = [
+    (GameOverAction::Rematch, "Rematch"),
+    (GameOverAction::NewGame, "New game"),
+    (GameOverAction::Review, "Review"),
+    (GameOverAction::SavePgn, "Save PGN"),
+    (GameOverAction::Quit, "Quit"),
+];
+
+fn create_resign_button(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    // Above the undo and redo buttons
+    spawn_side_button(commands, &asset_server, &mut materials, "Resign", 75.).with(ResignButton);
+}
+
+fn despawn_resign_button(commands: &mut Commands, query: Query<Entity, With<ResignButton>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// A button on the right above the undo and redo buttons, `bottom` pixels from the bottom.
+fn spawn_side_button<'a>(
+    commands: &'a mut Commands,
+    asset_server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    label: &str,
+    bottom: f32,
+) -> &'a mut Commands {
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(15.),
+                    bottom: Val::Px(bottom),
+                    ..Default::default()
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text {
+                    value: label.to_string(),
+                    font: asset_server.load("fonts/DejaVuSans.ttf"),
+                    style: TextStyle {
+                        font_size: 30.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            });
+        })
+}
+
+/// Against the computer the player resigns whenever they like, otherwise the side to move does.
+fn resign_button(
+    mut game: ResMut<Game>,
+    pending_promotion: Res<PendingPromotion>,
+    ai_settings: Res<AiSettings>,
+    engine: Res<Engine>,
+    engine_settings: Res<EngineSettings>,
+    interaction_query: Query<&Interaction, (Mutated<Interaction>, With<ResignButton>)>,
+) {
+    if game.is_over() || pending_promotion.mv.is_some() {
+        return;
+    }
+
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        let side_to_move = game.position().side_to_move();
+        let computer_plays =
+            |color| ai_settings.plays(color) || engine.plays(&engine_settings, color);
+        let resigning = if computer_plays(side_to_move) {
+            side_to_move.opposite()
+        } else {
+            side_to_move
+        };
+        game.resign(resigning);
+    }
+}
+
+/// Puts up the claim draw button while the position has come up three times or fifty moves
+/// have gone by without a capture or pawn move, and takes it away once that's no longer true.
+fn show_claim_draw(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    button_query: Query<Entity, With<ClaimDrawButton>>,
+) {
+    let shown = button_query.iter().next();
+    match (game.claimable_draw(), shown) {
+        (Some(_), None) => {
+            spawn_side_button(commands, &asset_server, &mut materials, "Draw", 135.)
+                .with(ClaimDrawButton);
+        }
+        (None, Some(entity)) => {
+            commands.despawn_recursive(entity);
+        }
+        _ => {}
+    }
+}
+
+/// Either player may claim the draw, whoever's turn it is.
+fn claim_draw_button(
+    mut game: ResMut<Game>,
+    pending_promotion: Res<PendingPromotion>,
+    interaction_query: Query<&Interaction, (Mutated<Interaction>, With<ClaimDrawButton>)>,
+) {
+    if pending_promotion.mv.is_some() {
+        return;
+    }
+
+    for interaction in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            game.claim_draw();
+        }
+    }
+}
+
+fn despawn_claim_draw_button(
+    commands: &mut Commands,
+    button_query: Query<Entity, With<ClaimDrawButton>>,
+) {
+    for entity in button_query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// Checkmate, stalemate, a draw, a resignation or running out of time ends the game.
+fn end_game(game: Res<Game>, mut state: ResMut<State<AppState>>) {
+    if game.is_over() {
+        change_state(&mut state, AppState::GameOver);
+    }
+}
+
+fn game_over_screen(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let outcome = match game.outcome() {
+        Some(outcome) => outcome,
+        None => return,
+    };
+
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    let button_material = materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                // Columns are laid out bottom up, so reverse them to put the result on top
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.5).into()),
+            ..Default::default()
+        })
+        .with(GameOverScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                text: Text {
+                    value: outcome_text(outcome),
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 60.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            });
+            parent
+                .spawn(NodeBundle {
+                    material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into()),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for (action, label) in GAME_OVER_CHOICES.iter() {
+                        parent
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(200.0), Val::Px(80.0)),
+                                    margin: Rect::all(Val::Px(10.0)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..Default::default()
+                                },
+                                material: button_material.clone(),
+                                ..Default::default()
+                            })
+                            .with(GameOverButton(*action))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle {
+                                    text: Text {
+                                        value: label.to_string(),
+                                        font: font.clone(),
+                                        style: TextStyle {
+                                            font_size: 40.0,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                            ..Default::default()
+                                        },
+                                    },
+                                    ..Default::default()
+                                });
+                            });
+                    }
+                });
+        });
+}
+
+fn despawn_game_over_screen(
+    commands: &mut Commands,
+    screen_query: Query<Entity, With<GameOverScreen>>,
+) {
+    for screen_entity in screen_query.iter() {
+        commands.despawn_recursive(screen_entity);
+    }
+}
+
+fn game_over_buttons(
+    mut state: ResMut<State<AppState>>,
+    mut game: ResMut<Game>,
+    mut replay: ResMut<Replay>,
+    mut ai_settings: ResMut<AiSettings>,
+    mut engine_settings: ResMut<EngineSettings>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    interaction_query: Query<(&Interaction, &GameOverButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button.0 {
+            GameOverAction::Rematch => {
+                if change_state(&mut state, AppState::Playing) {
+                    ai_settings.color = ai_settings.color.map(|color| color.opposite());
+                    engine_settings.color = engine_settings.color.opposite();
+                    *game = Game::new(game.starting_position().clone());
+                }
+            }
+            GameOverAction::NewGame => {
+                change_state(&mut state, AppState::MainMenu);
+            }
+            GameOverAction::Review => {
+                if change_state(&mut state, AppState::Review) {
+                    replay.push_finished(&game);
+                }
+            }
+            GameOverAction::SavePgn => save_pgn(&game),
+            GameOverAction::Quit => app_exit_events.send(AppExit),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use chess_core::Game;
+
+use crate::{
+    ai::AiSettings,
+    engine::{Engine, EngineSettings},
+    promotion::PendingPromotion,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+/// Taking moves back and playing them again, with Ctrl+Z and Ctrl+Y or the buttons in the
+/// bottom right corner. The pieces slide back through `sync_pieces` and `move_pieces`.
+pub struct HistoryPlugin;
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.on_state_update(APP_STATE_STAGE, AppState::Playing, history_keys.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, history_buttons.system())
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_history_buttons.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_history_buttons.system(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+struct HistoryButton(HistoryAction);
+
+/// Root node holding the undo and redo buttons.
+struct HistoryButtons;
+
+fn create_history_buttons(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    let button_material = materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into()),
+            ..Default::default()
+        })
+        .with(HistoryButtons)
+        .with_children(|parent| {
+            for (action, label) in
+                [(HistoryAction::Undo, "Undo"), (HistoryAction::Redo, "Redo")].iter()
+            {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(120.0), Val::Px(50.0)),
+                            margin: Rect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(HistoryButton(*action))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: Text {
+                                value: label.to_string(),
+                                font: font.clone(),
+                                style: TextStyle {
+                                    font_size: 30.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                    ..Default::default()
+                                },
+                            },
+                            ..Default::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn despawn_history_buttons(commands: &mut Commands, query: Query<Entity, With<HistoryButtons>>) {
+    for entity in query.iter() {
+        commands.despawn_recursive(entity);
+    }
+}
+
+fn history_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut game: ResMut<Game>,
+    pending_promotion: Res<PendingPromotion>,
+    ai_settings: Res<AiSettings>,
+    engine: Res<Engine>,
+    engine_settings: Res<EngineSettings>,
+) {
+    let ctrl =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+    let action = if !ctrl {
+        return;
+    } else if keyboard_input.just_pressed(KeyCode::Y)
+        || (shift && keyboard_input.just_pressed(KeyCode::Z))
+    {
+        HistoryAction::Redo
+    } else if keyboard_input.just_pressed(KeyCode::Z) {
+        HistoryAction::Undo
+    } else {
+        return;
+    };
+
+    if pending_promotion.mv.is_none() {
+        let computer_plays = |game: &Game| {
+            let color = game.position().side_to_move();
+            ai_settings.plays(color) || engine.plays(&engine_settings, color)
+        };
+        step_history(&mut game, action, computer_plays);
+    }
+}
+
+fn history_buttons(
+    mut game: ResMut<Game>,
+    pending_promotion: Res<PendingPromotion>,
+    ai_settings: Res<AiSettings>,
+    engine: Res<Engine>,
+    engine_settings: Res<EngineSettings>,
+    interaction_query: Query<(&Interaction, &HistoryButton), Mutated<Interaction>>,
+) {
+    if pending_promotion.mv.is_some() {
+        return;
+    }
+
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            let computer_plays = |game: &Game| {
+                let color = game.position().side_to_move();
+                ai_settings.plays(color) || engine.plays(&engine_settings, color)
+            };
+            step_history(&mut game, button.0, computer_plays);
+        }
+    }
+}
+
+/// Undoes or redoes a move. Against the computer this goes on until it's the player's turn
+/// again, or the computer would just play its move straight back.
+fn step_history(
+    game: &mut ResMut<Game>,
+    action: HistoryAction,
+    computer_plays: impl Fn(&Game) -> bool,
+) {
+    // Only touch the game when there's something to do, so the board isn't synced for nothing
+    let can_step = match action {
+        HistoryAction::Undo => game.can_undo(),
+        HistoryAction::Redo => game.can_redo(),
+    };
+    if !can_step {
+        return;
+    }
+
+    loop {
+        let stepped = match action {
+            HistoryAction::Undo => game.undo(),
+            HistoryAction::Redo => game.redo(),
+        };
+        if stepped.is_none() || !computer_plays(&game) {
+            break;
+        }
+    }
+}
@@ -1,13 +1,108 @@
 // From: https://caballerocoll.com/blog/bevy-chess-tutorial/
+use std::sync::Arc;
+
 use bevy::prelude::*;
 use bevy_mod_picking::*;
+use chess_core::{
+    book::Book, clock::TimeControl, pgn, tablebase::Tablebase, Game, PieceColor, Position,
+};
 
+mod engine;
+use engine::{Engine, EnginePlugin, EngineSettings, SearchLimit, UciEngine};
 mod pieces;
 use pieces::PiecesPlugin;
+mod ai;
+use ai::{Ai, AiPlugin, AiSettings, Difficulty};
 mod board;
 use board::BoardPlugin;
+mod clock;
+use clock::{ClockPlugin, GameClock};
+mod explorer;
+use explorer::ExplorerPlugin;
+mod game_over;
+use game_over::GameOverPlugin;
+mod history;
+use history::HistoryPlugin;
+mod menu;
+use menu::{MenuPlugin, StartingPosition};
+mod move_list;
+use move_list::MoveListPlugin;
+mod promotion;
+use promotion::{PromotionPlugin, PromotionSettings};
+mod replay;
+use replay::{Replay, ReplayPlugin};
+mod state;
+use state::{AppState, APP_STATE_STAGE};
 
 fn main() {
+    let position = match starting_position() {
+        Ok(position) => position,
+        Err(err) => {
+            eprintln!("Couldn't set up the board: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let replay = match load_replay() {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("Couldn't load the PGN file: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let engine_settings = match engine_settings() {
+        Ok(engine_settings) => engine_settings,
+        Err(err) => {
+            eprintln!("Invalid engine settings: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let ai_settings = match ai_settings() {
+        Ok(ai_settings) => ai_settings,
+        Err(err) => {
+            eprintln!("Invalid AI settings: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let ai = match ai() {
+        Ok(ai) => ai,
+        Err(err) => {
+            eprintln!("Invalid AI settings: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let engine = match &engine_settings.path {
+        Some(path) => match UciEngine::start(path) {
+            Ok(uci) => Engine::new(uci),
+            Err(err) => {
+                eprintln!("Couldn't start the engine {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        },
+        None => Engine::default(),
+    };
+    let game_clock = match game_clock() {
+        Ok(game_clock) => game_clock,
+        Err(err) => {
+            eprintln!("Invalid clock settings: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let book = match opening_book() {
+        Ok(book) => book,
+        Err(err) => {
+            eprintln!("Couldn't load the opening books: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let tablebase = match tablebase() {
+        Ok(tablebase) => tablebase,
+        Err(err) => {
+            eprintln!("Couldn't open the tablebases: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let game = Game::new(position.clone());
+
     App::build()
         .add_resource(Msaa { samples: 4 })
         .add_resource(WindowDescriptor {
@@ -16,10 +111,39 @@ fn main() {
             height: 1600.0,
             ..Default::default()
         })
+        .add_resource(PromotionSettings {
+            auto_queen: std::env::args().any(|arg| arg == "--auto-queen"),
+        })
+        .add_resource(StartingPosition(position))
+        .add_resource(game)
+        .add_resource(replay)
+        .add_resource(engine_settings)
+        .add_resource(engine)
+        .add_resource(ai_settings)
+        .add_resource(ai)
+        .add_resource(game_clock)
+        .add_resource(book)
+        .add_resource(Arc::new(tablebase))
         .add_plugins(DefaultPlugins)
+        .add_resource(State::new(AppState::MainMenu))
+        .add_stage_before(
+            stage::UPDATE,
+            APP_STATE_STAGE,
+            StateStage::<AppState>::default(),
+        )
         .add_plugin(PickingPlugin)
+        .add_plugin(MenuPlugin)
         .add_plugin(BoardPlugin)
         .add_plugin(PiecesPlugin)
+        .add_plugin(PromotionPlugin)
+        .add_plugin(MoveListPlugin)
+        .add_plugin(HistoryPlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(ExplorerPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(EnginePlugin)
+        .add_plugin(AiPlugin)
         .add_startup_system(setup.system())
         .run();
 }
@@ -39,5 +163,175 @@ fn setup(commands: &mut Commands) {
         .spawn(LightBundle {
             transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
             ..Default::default()
-        });
+        })
+        // UI camera for overlays like the promotion chooser
+        .spawn(CameraUiBundle::default());
+}
+
+/// Returns the value following `name` on the command line, e.g. the FEN in `--fen <FEN>`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next();
+    args.next()
+}
+
+/// The game starts from the FEN given with `--fen`, or read from the text file given with
+/// `--fen-file`, or from the usual starting position.
+fn starting_position() -> Result<Position, String> {
+    let fen = match (arg_value("--fen"), arg_value("--fen-file")) {
+        (Some(fen), _) => fen,
+        (None, Some(path)) => {
+            std::fs::read_to_string(&path).map_err(|err| format!("can't read {}: {}", path, err))?
+        }
+        (None, None) => return Ok(Position::starting()),
+    };
+    Position::from_fen(fen.trim()).map_err(|err| format!("invalid FEN: {}", err))
+}
+
+/// Games to step through from the main menu, read from the PGN file given with `--pgn`.
+/// `--game <n>` picks which game in the file to show first, counting from 1.
+fn load_replay() -> Result<Replay, String> {
+    let path = match arg_value("--pgn") {
+        Some(path) => path,
+        None => return Ok(Replay::default()),
+    };
+    let text =
+        std::fs::read_to_string(&path).map_err(|err| format!("can't read {}: {}", path, err))?;
+    let games = pgn::read_pgn(&text).map_err(|err| format!("{}: {}", path, err))?;
+    if games.is_empty() {
+        return Err(format!("{} has no games in it", path));
+    }
+
+    let game_number = match arg_value("--game") {
+        Some(number) => number
+            .parse::<usize>()
+            .ok()
+            .filter(|number| (1..=games.len()).contains(number))
+            .ok_or_else(|| {
+                format!(
+                    "invalid game number `{}`, {} has {} games",
+                    number,
+                    path,
+                    games.len()
+                )
+            })?,
+        None => 1,
+    };
+    Ok(Replay::new(games, game_number - 1))
+}
+
+/// Playing against a UCI engine: `--engine <path>` to the executable, `--engine-color
+/// <white|black>` for the side it plays, and `--movetime <ms>` or `--depth <plies>` for how long
+/// it thinks about each move.
+fn engine_settings() -> Result<EngineSettings, String> {
+    let mut settings = EngineSettings {
+        path: arg_value("--engine").map(Into::into),
+        ..Default::default()
+    };
+    if let Some(color) = arg_value("--engine-color") {
+        settings.color = match color.as_str() {
+            "white" => PieceColor::White,
+            "black" => PieceColor::Black,
+            _ => return Err(format!("invalid engine color `{}`", color)),
+        };
+    }
+    if let Some(movetime) = arg_value("--movetime") {
+        let movetime = movetime
+            .parse()
+            .map_err(|_| format!("invalid movetime `{}`", movetime))?;
+        settings.limit = SearchLimit::MoveTime(movetime);
+    }
+    if let Some(depth) = arg_value("--depth") {
+        let depth = depth
+            .parse()
+            .map_err(|_| format!("invalid depth `{}`", depth))?;
+        settings.limit = SearchLimit::Depth(depth);
+    }
+    Ok(settings)
+}
+
+/// Playing against the built-in AI: `--ai <white|black>` for the side it plays,
+/// `--difficulty <easy|medium|hard|expert>`, and `--no-book` to make it think about its opening
+/// moves instead of playing them from the book.
+fn ai_settings() -> Result<AiSettings, String> {
+    let mut settings = AiSettings {
+        book: !std::env::args().any(|arg| arg == "--no-book"),
+        ..Default::default()
+    };
+    if let Some(color) = arg_value("--ai") {
+        settings.color = match color.as_str() {
+            "white" => Some(PieceColor::White),
+            "black" => Some(PieceColor::Black),
+            _ => return Err(format!("invalid AI color `{}`", color)),
+        };
+    }
+    if let Some(difficulty) = arg_value("--difficulty") {
+        settings.difficulty = Difficulty::from_name(&difficulty)
+            .ok_or_else(|| format!("invalid difficulty `{}`", difficulty))?;
+    }
+    Ok(settings)
+}
+
+/// `--seed <number>` makes the built-in AI pick the same book moves every time it's run with it.
+fn ai() -> Result<Ai, String> {
+    match arg_value("--seed") {
+        Some(seed) => seed
+            .parse()
+            .map(Ai::with_seed)
+            .map_err(|_| format!("invalid seed `{}`", seed)),
+        None => Ok(Ai::default()),
+    }
+}
+
+/// Timed games: `--time <control>`, e.g. `5+3` for five minutes with three seconds added each
+/// move, `15d10` for a ten second Bronstein delay or `40/90,30+30` for a classical control.
+fn game_clock() -> Result<GameClock, String> {
+    let control = match arg_value("--time") {
+        Some(control) => Some(
+            TimeControl::parse(&control)
+                .map_err(|err| format!("invalid time control `{}`: {}", control, err))?,
+        ),
+        None => None,
+    };
+    Ok(GameClock {
+        control,
+        ..Default::default()
+    })
+}
+
+/// Folder the Polyglot opening books are read from.
+const BOOK_DIR: &str = "assets/books";
+
+/// Every `.bin` book in the books folder, merged into one. Without any, there are no book moves.
+fn opening_book() -> Result<Book, String> {
+    let entries = match std::fs::read_dir(BOOK_DIR) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Book::default()),
+        Err(err) => return Err(format!("can't read {}: {}", BOOK_DIR, err)),
+    };
+
+    let mut book = Book::default();
+    for entry in entries {
+        let path = entry
+            .map_err(|err| format!("can't read {}: {}", BOOK_DIR, err))?
+            .path();
+        if path.extension() != Some("bin".as_ref()) {
+            continue;
+        }
+        let bytes = std::fs::read(&path)
+            .map_err(|err| format!("can't read {}: {}", path.display(), err))?;
+        let file_book =
+            Book::from_bytes(&bytes).map_err(|err| format!("{}: {}", path.display(), err))?;
+        book.merge(file_book);
+    }
+    Ok(book)
+}
+
+/// Syzygy tablebases from the folder given with `--syzygy <dir>`, for the built-in AI to play
+/// endgames perfectly and to show the result once few enough pieces are left.
+fn tablebase() -> Result<Tablebase, String> {
+    match arg_value("--syzygy") {
+        Some(dir) => Tablebase::open(&dir).map_err(|err| format!("can't read {}: {}", dir, err)),
+        None => Ok(Tablebase::default()),
+    }
 }
@@ -0,0 +1,156 @@
+use bevy::{app::AppExit, prelude::*};
+use chess_core::{Game, Position};
+
+use crate::{
+    replay::Replay,
+    state::{change_state, AppState, APP_STATE_STAGE},
+};
+
+/// The screen the app starts on, for starting a game or reviewing the loaded games.
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.on_state_enter(
+            APP_STATE_STAGE,
+            AppState::MainMenu,
+            create_main_menu.system(),
+        )
+        .on_state_update(APP_STATE_STAGE, AppState::MainMenu, menu_buttons.system())
+        .on_state_exit(
+            APP_STATE_STAGE,
+            AppState::MainMenu,
+            despawn_main_menu.system(),
+        );
+    }
+}
+
+/// Where games started from the main menu begin, the usual starting position unless `--fen` or
+/// `--fen-file` said otherwise.
+pub struct StartingPosition(pub Position);
+
+/// Root node of the main menu.
+struct MainMenu;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuAction {
+    Play,
+    /// Step through the games from the PGN file, or the last game played.
+    Review,
+    Quit,
+}
+
+struct MenuButton(MenuAction);
+
+fn create_main_menu(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    replay: Res<Replay>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    let button_material = materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+    let mut choices = vec![(MenuAction::Play, "Play")];
+    if replay.has_games() {
+        choices.push((MenuAction::Review, "Review"));
+    }
+    choices.push((MenuAction::Quit, "Quit"));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                // Columns are laid out bottom up, so reverse them to put the title on top
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgb(0.05, 0.05, 0.05).into()),
+            ..Default::default()
+        })
+        .with(MainMenu)
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(40.0)),
+                    ..Default::default()
+                },
+                text: Text {
+                    value: "Chess :D".to_string(),
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 100.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            });
+            for (action, label) in choices {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(300.0), Val::Px(80.0)),
+                            margin: Rect::all(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(MenuButton(action))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: Text {
+                                value: label.to_string(),
+                                font: font.clone(),
+                                style: TextStyle {
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                    ..Default::default()
+                                },
+                            },
+                            ..Default::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn menu_buttons(
+    mut state: ResMut<State<AppState>>,
+    mut game: ResMut<Game>,
+    starting_position: Res<StartingPosition>,
+    replay: Res<Replay>,
+    mut app_exit_events: ResMut<Events<AppExit>>,
+    interaction_query: Query<(&Interaction, &MenuButton), Mutated<Interaction>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button.0 {
+            MenuAction::Play => {
+                if change_state(&mut state, AppState::Playing) {
+                    *game = Game::new(starting_position.0.clone());
+                }
+            }
+            MenuAction::Review => {
+                // Set up the board for the game being reviewed rather than the last one played
+                if change_state(&mut state, AppState::Review) {
+                    *game = replay.game();
+                }
+            }
+            MenuAction::Quit => app_exit_events.send(AppExit),
+        }
+    }
+}
+
+fn despawn_main_menu(commands: &mut Commands, menu_query: Query<Entity, With<MainMenu>>) {
+    for menu_entity in menu_query.iter() {
+        commands.despawn_recursive(menu_entity);
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::{prelude::*, window::ReceivedCharacter};
+use chess_core::{san, Game, PieceColor};
+
+use crate::{
+    ai::AiSettings,
+    board::play_move,
+    engine::{Engine, EngineSettings},
+    promotion::PendingPromotion,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+pub struct MoveListPlugin;
+impl Plugin for MoveListPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MoveEntry>()
+            .on_state_enter(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                create_move_list.system(),
+            )
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                despawn_move_list.system(),
+            )
+            .on_state_enter(APP_STATE_STAGE, AppState::Review, create_move_list.system())
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Review,
+                despawn_move_list.system(),
+            )
+            .add_system(update_move_list.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Playing, move_entry.system())
+            .on_state_exit(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                close_move_entry.system(),
+            );
+    }
+}
+
+/// Only the most recent moves fit on screen.
+const MAX_MOVE_LIST_LINES: usize = 30;
+
+/// A move being typed in, like `Nf3` or `exd5`. Enter starts typing, Enter again plays the move
+/// and Escape cancels.
+#[derive(Default)]
+pub struct MoveEntry {
+    /// What has been typed so far, `None` when not typing.
+    pub text: Option<String>,
+    /// Why the last move typed couldn't be played.
+    pub error: Option<String>,
+}
+
+impl MoveEntry {
+    pub fn is_active(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+/// Text on the right side of the screen listing the moves played.
+struct MoveListText;
+
+/// Text at the bottom of the screen showing the move being typed.
+struct MoveEntryText;
+
+fn create_move_list(commands: &mut Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font: font.clone(),
+                style: TextStyle {
+                    font_size: 30.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(MoveListText)
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                value: String::new(),
+                font,
+                style: TextStyle {
+                    font_size: 40.0,
+                    color: Color::rgb(0.8, 0.8, 0.8),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        })
+        .with(MoveEntryText);
+}
+
+fn despawn_move_list(
+    commands: &mut Commands,
+    list_query: Query<Entity, With<MoveListText>>,
+    entry_query: Query<Entity, With<MoveEntryText>>,
+) {
+    for entity in list_query.iter().chain(entry_query.iter()) {
+        commands.despawn_recursive(entity);
+    }
+}
+
+/// One line per full move in SAN, e.g. `1. e4 e5`.
+fn move_list_lines(game: &Game) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut position = game.starting_position().clone();
+    for mv in game.moves() {
+        let san = san::to_san(&position, *mv);
+        match (position.side_to_move(), lines.last_mut()) {
+            (PieceColor::Black, Some(line)) => {
+                line.push(' ');
+                line.push_str(&san);
+            }
+            (PieceColor::Black, None) => {
+                lines.push(format!("{}... {}", position.fullmove_number(), san))
+            }
+            (PieceColor::White, _) => {
+                lines.push(format!("{}. {}", position.fullmove_number(), san))
+            }
+        }
+        position.make_move(*mv);
+    }
+    lines
+}
+
+fn update_move_list(game: ChangedRes<Game>, mut query: Query<&mut Text, With<MoveListText>>) {
+    let lines = move_list_lines(&game);
+    let shown = &lines[lines.len().saturating_sub(MAX_MOVE_LIST_LINES)..];
+    for mut text in query.iter_mut() {
+        text.value = shown.join("\n");
+    }
+}
+
+fn move_entry(
+    keyboard_input: Res<Input<KeyCode>>,
+    received_characters: Res<Events<ReceivedCharacter>>,
+    mut character_reader: Local<EventReader<ReceivedCharacter>>,
+    mut entry: ResMut<MoveEntry>,
+    mut game: ResMut<Game>,
+    mut pending_promotion: ResMut<PendingPromotion>,
+    engine: Res<Engine>,
+    engine_settings: Res<EngineSettings>,
+    ai_settings: Res<AiSettings>,
+    mut entry_query: Query<&mut Text, With<MoveEntryText>>,
+) {
+    // Read the characters every frame so old ones don't show up once typing starts. Shortcuts
+    // like Ctrl+Z aren't typed.
+    let ctrl =
+        keyboard_input.pressed(KeyCode::LControl) || keyboard_input.pressed(KeyCode::RControl);
+    let typed: String = character_reader
+        .iter(&received_characters)
+        .map(|event| event.char)
+        .filter(|c| !ctrl && (c.is_ascii_alphanumeric() || "-=+#".contains(*c)))
+        .collect();
+
+    match entry.text.clone() {
+        None => {
+            if keyboard_input.just_pressed(KeyCode::Return)
+                && pending_promotion.mv.is_none()
+                && !game.is_over()
+                && !engine.plays(&engine_settings, game.position().side_to_move())
+                && !ai_settings.plays(game.position().side_to_move())
+            {
+                entry.text = Some(String::new());
+                entry.error = None;
+            }
+        }
+        Some(mut text) => {
+            if keyboard_input.just_pressed(KeyCode::Escape) {
+                entry.text = None;
+                entry.error = None;
+            } else if keyboard_input.just_pressed(KeyCode::Return) {
+                match san::parse_san(game.position(), &text) {
+                    Ok(mv) => {
+                        play_move(&mut game, &mut pending_promotion, mv);
+                        entry.text = None;
+                        entry.error = None;
+                    }
+                    Err(err) => entry.error = Some(err.to_string()),
+                }
+            } else {
+                if keyboard_input.just_pressed(KeyCode::Back) {
+                    text.pop();
+                }
+                text.push_str(&typed);
+                entry.text = Some(text);
+            }
+        }
+    }
+
+    let value = match (&entry.text, &entry.error) {
+        (Some(text), Some(error)) => format!("Move: {}_  ({})", text, error),
+        (Some(text), None) => format!("Move: {}_", text),
+        (None, _) => String::new(),
+    };
+    for mut text in entry_query.iter_mut() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+/// Drops a half typed move when the game ends or is left.
+fn close_move_entry(mut entry: ResMut<MoveEntry>) {
+    entry.text = None;
+    entry.error = None;
+}
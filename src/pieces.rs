@@ -1,31 +1,25 @@
 use bevy::prelude::*;
 
+use chess_core::Game;
+pub use chess_core::{PieceColor, PieceType};
+
+use crate::state::{AppState, APP_STATE_STAGE};
+
 pub struct PiecesPlugin;
 
 impl Plugin for PiecesPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(create_pieces.system())
-            .add_system(move_pieces.system());
+        app.init_resource::<PieceAssets>()
+            .on_state_exit(APP_STATE_STAGE, AppState::MainMenu, create_pieces.system())
+            .on_state_enter(APP_STATE_STAGE, AppState::MainMenu, despawn_pieces.system())
+            .add_system(sync_pieces.system())
+            .add_system(move_pieces.system())
+            .add_system(move_captured_pieces.system());
     }
 }
 
-// TODO Maybe split piece structs into it's own file
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceColor {
-    White,
-    Black,
-}
-
-#[derive(Clone, Copy, PartialEq)]
-pub enum PieceType {
-    King,
-    Queen,
-    Bishop,
-    Knight,
-    Rook,
-    Pawn,
-}
-
+/// A piece on the 3D board. The rules live in `chess_core`, this only mirrors the game's position
+/// so the piece can be drawn on the right square.
 #[derive(Clone, Copy)]
 pub struct Piece {
     pub color: PieceColor,
@@ -34,537 +28,280 @@ pub struct Piece {
     pub x: u8,
     pub y: u8,
 }
-impl Piece {
-    /// Returns the possible_positions that are available
-    //TODO(Sahil) - impl en passant, castling
-    pub fn is_move_valid(&self, new_position: (u8, u8), pieces: Vec<Piece>) -> bool {
-        // If there's a piece of the same color in the same square, it can't move
-        if color_of_piece_on_square(new_position, &pieces) == Some(self.color) {
-            return false;
-        }
-
-        match self.piece_type {
-            PieceType::King => {
-                // Horizontal
-                ((self.x as i8 - new_position.0 as i8).abs() == 1
-                    && (self.y == new_position.1))
-                // Vertical
-                || ((self.y as i8 - new_position.1 as i8).abs() == 1
-                    && (self.x == new_position.0))
-                // Diagonal
-                || ((self.x as i8 - new_position.0 as i8).abs() == 1
-                    && (self.y as i8 - new_position.1 as i8).abs() == 1)
-            }
-            PieceType::Queen => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && ((self.x as i8 - new_position.0 as i8).abs()
-                        == (self.y as i8 - new_position.1 as i8).abs()
-                        || ((self.x == new_position.0 && self.y != new_position.1)
-                            || (self.y == new_position.1 && self.x != new_position.0)))
-            }
-            PieceType::Bishop => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && (self.x as i8 - new_position.0 as i8).abs()
-                        == (self.y as i8 - new_position.1 as i8).abs()
-            }
-            PieceType::Knight => {
-                ((self.x as i8 - new_position.0 as i8).abs() == 2
-                    && (self.y as i8 - new_position.1 as i8).abs() == 1)
-                    || ((self.x as i8 - new_position.0 as i8).abs() == 1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 2)
-            }
-            PieceType::Rook => {
-                is_path_empty((self.x, self.y), new_position, &pieces)
-                    && ((self.x == new_position.0 && self.y != new_position.1)
-                        || (self.y == new_position.1 && self.x != new_position.0))
-            }
-            PieceType::Pawn => {
-                if self.color == PieceColor::White {
-                    // Normal move
-                    if new_position.0 as i8 - self.x as i8 == 1 && (self.y == new_position.1) {
-                        if color_of_piece_on_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
 
-                    // Move 2 squares
-                    if self.x == 1
-                        && new_position.0 as i8 - self.x as i8 == 2
-                        && (self.y == new_position.1)
-                        && is_path_empty((self.x, self.y), new_position, &pieces)
-                    {
-                        if color_of_piece_on_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Take piece
-                    if new_position.0 as i8 - self.x as i8 == 1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 1
-                    {
-                        if color_of_piece_on_square(new_position, &pieces)
-                            == Some(PieceColor::Black)
-                        {
-                            return true;
-                        }
-                    }
-                } else {
-                    // Normal move
-                    if new_position.0 as i8 - self.x as i8 == -1 && (self.y == new_position.1) {
-                        if color_of_piece_on_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Move 2 squares
-                    if self.x == 6
-                        && new_position.0 as i8 - self.x as i8 == -2
-                        && (self.y == new_position.1)
-                        && is_path_empty((self.x, self.y), new_position, &pieces)
-                    {
-                        if color_of_piece_on_square(new_position, &pieces).is_none() {
-                            return true;
-                        }
-                    }
-
-                    // Take piece
-                    if new_position.0 as i8 - self.x as i8 == -1
-                        && (self.y as i8 - new_position.1 as i8).abs() == 1
-                    {
-                        if color_of_piece_on_square(new_position, &pieces)
-                            == Some(PieceColor::White)
-                        {
-                            return true;
-                        }
-                    }
-                }
+/// A piece that was captured, standing beside the board in case the capture is taken back.
+/// Captured pieces don't have a `Piece` component.
+#[derive(Clone, Copy)]
+pub struct CapturedPiece {
+    pub color: PieceColor,
+    pub piece_type: PieceType,
+    /// Place in the row of captured pieces of this color.
+    pub slot: u8,
+}
 
-                false
-            }
-        }
+impl CapturedPiece {
+    /// White's captured pieces line up past the a-file, Black's past the h-file.
+    fn translation(&self) -> Vec3 {
+        let y = match self.color {
+            PieceColor::White => -1.5,
+            PieceColor::Black => 8.5,
+        };
+        Vec3::new(self.slot as f32 * 0.5, 0.0, y)
     }
 }
 
 fn move_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &Piece)>) {
     // System to actually move the pieces.
     for (mut transform, piece) in query.iter_mut() {
-        let direction = Vec3::new(piece.x as f32, 0.0, piece.y as f32) - transform.translation;
-        if direction.length() > 0.1 {
-            transform.translation += direction.normalize() * time.delta_seconds();
-        }
+        let target = Vec3::new(piece.x as f32, 0.0, piece.y as f32);
+        slide_towards(&mut transform, target, time.delta_seconds());
     }
 }
 
-fn color_of_piece_on_square(pos: (u8, u8), pieces: &Vec<Piece>) -> Option<PieceColor> {
-    for piece in pieces {
-        if piece.x == pos.0 && piece.y == pos.1 {
-            return Some(piece.color);
-        }
+fn move_captured_pieces(time: Res<Time>, mut query: Query<(&mut Transform, &CapturedPiece)>) {
+    for (mut transform, captured) in query.iter_mut() {
+        slide_towards(&mut transform, captured.translation(), time.delta_seconds());
     }
-    None
 }
 
-fn is_path_empty(begin: (u8, u8), end: (u8, u8), pieces: &Vec<Piece>) -> bool {
-    // Same column
-    if begin.0 == end.0 {
-        for piece in pieces {
-            if piece.x == begin.0
-                && ((piece.y > begin.1 && piece.y < end.1)
-                    || (piece.y > end.1 && piece.y < begin.1))
-            {
-                return false;
-            }
-        }
+fn slide_towards(transform: &mut Transform, target: Vec3, delta_seconds: f32) {
+    let direction = target - transform.translation;
+    if direction.length() > 0.1 {
+        transform.translation += direction.normalize() * delta_seconds;
+    }
+}
+
+/// Brings the piece entities in line with the game's position after a move, or after taking
+/// moves back. Pieces already on the right square stay put, moved pieces slide over in
+/// `move_pieces`, promoted pieces get new meshes, and captured pieces move beside the board and
+/// come back if the capture is undone. There are no pieces to sync in the main menu, they are
+/// created from the game once it's left.
+fn sync_pieces(
+    commands: &mut Commands,
+    game: ChangedRes<Game>,
+    state: Res<State<AppState>>,
+    piece_assets: Res<PieceAssets>,
+    mut query: Query<(Entity, &mut Piece, &Children)>,
+    captured_query: Query<(Entity, &CapturedPiece)>,
+) {
+    if *state.current() == AppState::MainMenu {
+        return;
     }
 
-    // Same row
-    if begin.1 == end.1 {
-        for piece in pieces {
-            if piece.y == begin.1
-                && ((piece.x > begin.0 && piece.x < end.0)
-                    || (piece.x > end.0 && piece.x < begin.0))
-            {
-                return false;
+    let mut unmatched: Vec<(Entity, Piece)> = query
+        .iter_mut()
+        .map(|(entity, piece, _)| (entity, *piece))
+        .collect();
+    let mut captured: Vec<(Entity, CapturedPiece)> = captured_query
+        .iter()
+        .map(|(entity, captured)| (entity, *captured))
+        .collect();
+
+    // First leave alone every piece that's already where it should be
+    let mut unplaced = Vec::new();
+    for (square, target) in game.position().pieces() {
+        let position = (square.rank(), square.file());
+        match unmatched.iter().position(|(_, piece)| {
+            (piece.x, piece.y) == position
+                && piece.color == target.color
+                && piece.piece_type == target.piece_type
+        }) {
+            Some(idx) => {
+                unmatched.swap_remove(idx);
             }
+            None => unplaced.push((position, target)),
         }
     }
 
-    // Diagonals
-    let x_diff = (begin.0 as i8 - end.0 as i8).abs();
-    let y_diff = (begin.1 as i8 - end.1 as i8).abs();
-    if x_diff == y_diff {
-        for i in 1..x_diff {
-            let pos = if begin.0 < end.0 && begin.1 < end.1 {
-                // left bottom - right top
-                (begin.0 + i as u8, begin.1 + i as u8)
-            } else if begin.0 < end.0 && begin.1 > end.1 {
-                // left top - right bottom
-                (begin.0 + i as u8, begin.1 - i as u8)
-            } else if begin.0 > end.0 && begin.1 < end.1 {
-                // right bottom - left top
-                (begin.0 - i as u8, begin.1 + i as u8)
-            } else {
-                // begin.0 > end.0 && begin.1 > end.1
-                // right top - left bottom
-                (begin.0 - i as u8, begin.1 - i as u8)
-            };
+    // Then move over the closest piece of the same kind, or a pawn that just promoted
+    for (position, target) in unplaced {
+        let distance = |piece: &Piece| {
+            (piece.x as i32 - position.0 as i32).pow(2)
+                + (piece.y as i32 - position.1 as i32).pow(2)
+        };
+        let closest = |is_candidate: &dyn Fn(&Piece) -> bool| {
+            unmatched
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, piece))| piece.color == target.color && is_candidate(piece))
+                .min_by_key(|(_, (_, piece))| distance(piece))
+                .map(|(idx, _)| idx)
+        };
+        let idx = closest(&|piece| piece.piece_type == target.piece_type).or_else(|| {
+            closest(&|piece| {
+                (piece.piece_type == PieceType::Pawn) != (target.piece_type == PieceType::Pawn)
+            })
+        });
 
-            if color_of_piece_on_square(pos, pieces).is_some() {
-                return false;
+        match idx {
+            Some(idx) => {
+                let (entity, _) = unmatched.swap_remove(idx);
+                if let Ok((_, mut piece, children)) = query.get_mut(entity) {
+                    piece.x = position.0;
+                    piece.y = position.1;
+                    if piece.piece_type != target.piece_type {
+                        piece.piece_type = target.piece_type;
+                        for child in children.iter() {
+                            commands.despawn_recursive(*child);
+                        }
+                        spawn_piece_meshes(
+                            commands,
+                            entity,
+                            &piece_assets,
+                            target.color,
+                            target.piece_type,
+                        );
+                    }
+                }
+            }
+            None => {
+                // Bring back a captured piece if there is one, otherwise it's a new piece
+                let piece = Piece {
+                    color: target.color,
+                    piece_type: target.piece_type,
+                    x: position.0,
+                    y: position.1,
+                };
+                match captured.iter().position(|(_, captured)| {
+                    captured.color == target.color && captured.piece_type == target.piece_type
+                }) {
+                    Some(idx) => {
+                        let (entity, _) = captured.swap_remove(idx);
+                        commands.remove_one::<CapturedPiece>(entity);
+                        commands.insert_one(entity, piece);
+                    }
+                    None => spawn_piece(
+                        commands,
+                        &piece_assets,
+                        target.color,
+                        target.piece_type,
+                        position,
+                    ),
+                }
             }
         }
     }
-    true
-}
 
-fn create_pieces(
-    commands: &mut Commands,
-    asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    // Load all the meshes
-    let king_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0");
-    let king_cross_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0");
-    let pawn_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0");
-    let knight_1_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0");
-    let knight_2_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0");
-    let rook_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0");
-    let bishop_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0");
-    let queen_handle: Handle<Mesh> =
-        asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0");
+    // Anything left over was captured, and goes in the first free place beside the board
+    for (entity, piece) in unmatched {
+        let slot = (0..)
+            .find(|slot| {
+                !captured
+                    .iter()
+                    .any(|(_, captured)| captured.color == piece.color && captured.slot == *slot)
+            })
+            .unwrap();
+        let captured_piece = CapturedPiece {
+            color: piece.color,
+            piece_type: piece.piece_type,
+            slot,
+        };
+        captured.push((entity, captured_piece));
+        commands.remove_one::<Piece>(entity);
+        commands.insert_one(entity, captured_piece);
+    }
+}
 
-    // Add some materials
-    let white_material = materials.add(Color::rgb(1., 0.8, 0.8).into());
-    let black_material = materials.add(Color::rgb(0., 0.2, 0.2).into());
+/// Mesh and material handles for every kind of piece, kept around so that pieces can have their
+/// meshes swapped out after the board is set up (e.g. on promotion).
+pub struct PieceAssets {
+    king: Handle<Mesh>,
+    king_cross: Handle<Mesh>,
+    pawn: Handle<Mesh>,
+    knight_1: Handle<Mesh>,
+    knight_2: Handle<Mesh>,
+    rook: Handle<Mesh>,
+    bishop: Handle<Mesh>,
+    queen: Handle<Mesh>,
+    white_material: Handle<StandardMaterial>,
+    black_material: Handle<StandardMaterial>,
+}
 
-    // White Side
-    spawn_rook(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        rook_handle.clone(),
-        (0, 0),
-    );
-    spawn_knight(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        (0, 1),
-    );
-    spawn_bishop(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        bishop_handle.clone(),
-        (0, 2),
-    );
-    spawn_queen(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        queen_handle.clone(),
-        (0, 3),
-    );
-    spawn_king(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        king_handle.clone(),
-        king_cross_handle.clone(),
-        (0, 4),
-    );
-    spawn_bishop(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        bishop_handle.clone(),
-        (0, 5),
-    );
-    spawn_knight(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        (0, 6),
-    );
-    spawn_rook(
-        commands,
-        white_material.clone(),
-        PieceColor::White,
-        rook_handle.clone(),
-        (0, 7),
-    );
+impl FromResources for PieceAssets {
+    fn from_resources(resources: &Resources) -> Self {
+        let asset_server = resources.get::<AssetServer>().unwrap();
+        let mut materials = resources.get_mut::<Assets<StandardMaterial>>().unwrap();
 
-    for idx in 0..8 {
-        spawn_pawn(
-            commands,
-            white_material.clone(),
-            PieceColor::White,
-            pawn_handle.clone(),
-            (1, idx),
-        );
+        PieceAssets {
+            // Load all the meshes
+            king: asset_server.load("models/chess_kit/pieces.glb#Mesh0/Primitive0"),
+            king_cross: asset_server.load("models/chess_kit/pieces.glb#Mesh1/Primitive0"),
+            pawn: asset_server.load("models/chess_kit/pieces.glb#Mesh2/Primitive0"),
+            knight_1: asset_server.load("models/chess_kit/pieces.glb#Mesh3/Primitive0"),
+            knight_2: asset_server.load("models/chess_kit/pieces.glb#Mesh4/Primitive0"),
+            rook: asset_server.load("models/chess_kit/pieces.glb#Mesh5/Primitive0"),
+            bishop: asset_server.load("models/chess_kit/pieces.glb#Mesh6/Primitive0"),
+            queen: asset_server.load("models/chess_kit/pieces.glb#Mesh7/Primitive0"),
+            // Add some materials
+            white_material: materials.add(Color::rgb(1., 0.8, 0.8).into()),
+            black_material: materials.add(Color::rgb(0., 0.2, 0.2).into()),
+        }
     }
+}
 
-    // Black Side
-    spawn_rook(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        rook_handle.clone(),
-        (7, 0),
-    );
-    spawn_knight(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        (7, 1),
-    );
-    spawn_bishop(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        bishop_handle.clone(),
-        (7, 2),
-    );
-    spawn_queen(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        queen_handle.clone(),
-        (7, 3),
-    );
-    spawn_king(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        king_handle.clone(),
-        king_cross_handle.clone(),
-        (7, 4),
-    );
-    spawn_bishop(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        bishop_handle.clone(),
-        (7, 5),
-    );
-    spawn_knight(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        knight_1_handle.clone(),
-        knight_2_handle.clone(),
-        (7, 6),
-    );
-    spawn_rook(
-        commands,
-        black_material.clone(),
-        PieceColor::Black,
-        rook_handle.clone(),
-        (7, 7),
-    );
-
-    for idx in 0..8 {
-        spawn_pawn(
-            commands,
-            black_material.clone(),
-            PieceColor::Black,
-            pawn_handle.clone(),
-            (6, idx),
-        );
+impl PieceAssets {
+    fn meshes(&self, piece_type: PieceType) -> Vec<Handle<Mesh>> {
+        match piece_type {
+            PieceType::King => vec![self.king.clone(), self.king_cross.clone()],
+            PieceType::Queen => vec![self.queen.clone()],
+            PieceType::Bishop => vec![self.bishop.clone()],
+            PieceType::Knight => vec![self.knight_1.clone(), self.knight_2.clone()],
+            PieceType::Rook => vec![self.rook.clone()],
+            PieceType::Pawn => vec![self.pawn.clone()],
+        }
     }
-}
 
-fn spawn_king(
-    commands: &mut Commands,
-    material: Handle<StandardMaterial>,
-    piece_color: PieceColor,
-    mesh: Handle<Mesh>,
-    mesh_cross: Handle<Mesh>,
-    position: (u8, u8),
-) {
-    commands
-        // Spawn parent entity
-        .spawn(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.0,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .with(Piece {
-            color: piece_color,
-            piece_type: PieceType::King,
-            x: position.0,
-            y: position.1,
-        })
-        // Add children to the parent
-        .with_children(|parent| {
-            parent.spawn(PbrBundle {
-                mesh: mesh.clone(),
-                material: material.clone(),
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., -1.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-            parent.spawn(PbrBundle {
-                mesh: mesh_cross.clone(),
-                material: material.clone(),
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., -1.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-        });
+    fn material(&self, piece_color: PieceColor) -> Handle<StandardMaterial> {
+        match piece_color {
+            PieceColor::White => self.white_material.clone(),
+            PieceColor::Black => self.black_material.clone(),
+        }
+    }
 }
 
-fn spawn_knight(
-    commands: &mut Commands,
-    material: Handle<StandardMaterial>,
-    piece_color: PieceColor,
-    mesh_1: Handle<Mesh>,
-    mesh_2: Handle<Mesh>,
-    position: (u8, u8),
-) {
-    commands
-        // Spawn parent entity
-        .spawn(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.0,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .with(Piece {
-            color: piece_color,
-            piece_type: PieceType::Knight,
-            x: position.0,
-            y: position.1,
-        })
-        // Add children to the parent
-        .with_children(|parent| {
-            parent.spawn(PbrBundle {
-                mesh: mesh_1,
-                material: material.clone(),
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., 0.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-            parent.spawn(PbrBundle {
-                mesh: mesh_2,
-                material,
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., 0.9));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-        });
+/// Offset of the meshes in pieces.glb from the square the piece stands on.
+fn mesh_offset(piece_type: PieceType) -> Vec3 {
+    match piece_type {
+        PieceType::King => Vec3::new(-0.2, 0., -1.9),
+        PieceType::Queen => Vec3::new(-0.2, 0., -0.95),
+        PieceType::Bishop => Vec3::new(-0.1, 0., 0.),
+        PieceType::Knight => Vec3::new(-0.2, 0., 0.9),
+        PieceType::Rook => Vec3::new(-0.1, 0., 1.8),
+        PieceType::Pawn => Vec3::new(-0.2, 0., 2.6),
+    }
 }
 
-fn spawn_queen(
-    commands: &mut Commands,
-    material: Handle<StandardMaterial>,
-    piece_color: PieceColor,
-    mesh: Handle<Mesh>,
-    position: (u8, u8),
-) {
-    commands
-        .spawn(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.0,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .with(Piece {
-            color: piece_color,
-            piece_type: PieceType::Queen,
-            x: position.0,
-            y: position.1,
-        })
-        .with_children(|parent| {
-            parent.spawn(PbrBundle {
-                mesh,
-                material,
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., -0.95));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-        });
+fn create_pieces(commands: &mut Commands, game: Res<Game>, piece_assets: Res<PieceAssets>) {
+    for (square, piece) in game.position().pieces() {
+        spawn_piece(
+            commands,
+            &piece_assets,
+            piece.color,
+            piece.piece_type,
+            (square.rank(), square.file()),
+        );
+    }
 }
 
-fn spawn_bishop(
+fn despawn_pieces(
     commands: &mut Commands,
-    material: Handle<StandardMaterial>,
-    piece_color: PieceColor,
-    mesh: Handle<Mesh>,
-    position: (u8, u8),
+    query: Query<Entity, With<Piece>>,
+    captured_query: Query<Entity, With<CapturedPiece>>,
 ) {
-    commands
-        .spawn(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.0,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .with(Piece {
-            color: piece_color,
-            piece_type: PieceType::Bishop,
-            x: position.0,
-            y: position.1,
-        })
-        .with_children(|parent| {
-            parent.spawn(PbrBundle {
-                mesh,
-                material,
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.1, 0., 0.));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
-        });
+    for entity in query.iter().chain(captured_query.iter()) {
+        commands.despawn_recursive(entity);
+    }
 }
 
-fn spawn_rook(
+fn spawn_piece(
     commands: &mut Commands,
-    material: Handle<StandardMaterial>,
+    piece_assets: &PieceAssets,
     piece_color: PieceColor,
-    mesh: Handle<Mesh>,
+    piece_type: PieceType,
     position: (u8, u8),
 ) {
     commands
+        // Spawn parent entity
         .spawn(PbrBundle {
             transform: Transform::from_translation(Vec3::new(
                 position.0 as f32,
@@ -575,56 +312,36 @@ fn spawn_rook(
         })
         .with(Piece {
             color: piece_color,
-            piece_type: PieceType::Rook,
+            piece_type,
             x: position.0,
             y: position.1,
-        })
-        .with_children(|parent| {
-            parent.spawn(PbrBundle {
-                mesh,
-                material,
-                transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.1, 0., 1.8));
-                    transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
-                    transform
-                },
-                ..Default::default()
-            });
         });
+    let entity = commands.current_entity().unwrap();
+    spawn_piece_meshes(commands, entity, piece_assets, piece_color, piece_type);
 }
 
-fn spawn_pawn(
+/// Adds the meshes for the given piece type as children of the piece entity.
+fn spawn_piece_meshes(
     commands: &mut Commands,
-    material: Handle<StandardMaterial>,
+    entity: Entity,
+    piece_assets: &PieceAssets,
     piece_color: PieceColor,
-    mesh: Handle<Mesh>,
-    position: (u8, u8),
+    piece_type: PieceType,
 ) {
-    commands
-        .spawn(PbrBundle {
-            transform: Transform::from_translation(Vec3::new(
-                position.0 as f32,
-                0.0,
-                position.1 as f32,
-            )),
-            ..Default::default()
-        })
-        .with(Piece {
-            color: piece_color,
-            piece_type: PieceType::Pawn,
-            x: position.0,
-            y: position.1,
-        })
-        .with_children(|parent| {
+    let material = piece_assets.material(piece_color);
+    commands.set_current_entity(entity);
+    commands.with_children(|parent| {
+        for mesh in piece_assets.meshes(piece_type) {
             parent.spawn(PbrBundle {
                 mesh,
-                material,
+                material: material.clone(),
                 transform: {
-                    let mut transform = Transform::from_translation(Vec3::new(-0.2, 0., 2.6));
+                    let mut transform = Transform::from_translation(mesh_offset(piece_type));
                     transform.apply_non_uniform_scale(Vec3::new(0.2, 0.2, 0.2));
                     transform
                 },
                 ..Default::default()
             });
-        });
+        }
+    });
 }
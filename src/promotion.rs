@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use chess_core::{Game, Move};
+
+use crate::{
+    pieces::PieceType,
+    state::{AppState, APP_STATE_STAGE},
+};
+
+pub struct PromotionPlugin;
+impl Plugin for PromotionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PromotionSettings>()
+            .init_resource::<PendingPromotion>()
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                open_promotion_chooser.system(),
+            )
+            .on_state_update(
+                APP_STATE_STAGE,
+                AppState::Playing,
+                promotion_buttons.system(),
+            );
+    }
+}
+
+#[derive(Default)]
+pub struct PromotionSettings {
+    /// Skip the chooser and always promote to a queen.
+    pub auto_queen: bool,
+}
+
+/// A pawn move to the last row that is waiting for the player to pick what the pawn becomes.
+#[derive(Default)]
+pub struct PendingPromotion {
+    pub mv: Option<Move>,
+}
+
+/// Root node of the chooser overlay.
+struct PromotionChooser;
+
+/// Button in the chooser overlay that promotes to the given piece type.
+struct PromotionButton(PieceType);
+
+const PROMOTION_CHOICES: [(PieceType, &str); 4] = [
+    (PieceType::Queen, "Queen"),
+    (PieceType::Rook, "Rook"),
+    (PieceType::Bishop, "Bishop"),
+    (PieceType::Knight, "Knight"),
+];
+
+fn open_promotion_chooser(
+    commands: &mut Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<PromotionSettings>,
+    mut game: ResMut<Game>,
+    mut pending: ResMut<PendingPromotion>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    chooser_query: Query<&PromotionChooser>,
+) {
+    let mv = match pending.mv {
+        Some(mv) => mv,
+        None => return,
+    };
+
+    if settings.auto_queen {
+        game.play(Move {
+            promotion: Some(PieceType::Queen),
+            ..mv
+        });
+        pending.mv = None;
+        return;
+    }
+
+    // The chooser is already up
+    if chooser_query.iter().next().is_some() {
+        return;
+    }
+
+    let font = asset_server.load("fonts/DejaVuSans.ttf");
+    let button_material = materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.5).into()),
+            ..Default::default()
+        })
+        .with(PromotionChooser)
+        .with_children(|parent| {
+            for (piece_type, label) in PROMOTION_CHOICES.iter() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.0), Val::Px(80.0)),
+                            margin: Rect::all(Val::Px(10.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .with(PromotionButton(*piece_type))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle {
+                            text: Text {
+                                value: label.to_string(),
+                                font: font.clone(),
+                                style: TextStyle {
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                    ..Default::default()
+                                },
+                            },
+                            ..Default::default()
+                        });
+                    });
+            }
+        });
+}
+
+fn promotion_buttons(
+    commands: &mut Commands,
+    mut game: ResMut<Game>,
+    mut pending: ResMut<PendingPromotion>,
+    interaction_query: Query<(&Interaction, &PromotionButton), Mutated<Interaction>>,
+    chooser_query: Query<Entity, With<PromotionChooser>>,
+) {
+    let mv = match pending.mv {
+        Some(mv) => mv,
+        None => return,
+    };
+
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        game.play(Move {
+            promotion: Some(button.0),
+            ..mv
+        });
+        pending.mv = None;
+        for chooser_entity in chooser_query.iter() {
+            commands.despawn_recursive(chooser_entity);
+        }
+        break;
+    }
+}
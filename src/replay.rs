@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use chess_core::{pgn::PgnGame, Game};
+
+use crate::state::{change_state, AppState, APP_STATE_STAGE};
+
+/// Steps through games loaded from a PGN file or the game just finished, instead of playing a
+/// new one.
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Replay>()
+            .on_state_enter(APP_STATE_STAGE, AppState::Review, start_review.system())
+            .on_state_update(APP_STATE_STAGE, AppState::Review, replay_controls.system());
+    }
+}
+
+/// The games to review and how far into the current one we are.
+#[derive(Default)]
+pub struct Replay {
+    pub games: Vec<PgnGame>,
+    pub game_index: usize,
+    /// Number of half-moves of the current game shown on the board.
+    pub ply: usize,
+}
+
+impl Replay {
+    pub fn new(games: Vec<PgnGame>, game_index: usize) -> Self {
+        Replay {
+            games,
+            game_index,
+            ply: 0,
+        }
+    }
+
+    /// Adds the game that was just played after the loaded ones, and shows it from where it
+    /// ended.
+    pub fn push_finished(&mut self, game: &Game) {
+        let tags = vec![
+            ("White".to_string(), "White".to_string()),
+            ("Black".to_string(), "Black".to_string()),
+        ];
+        self.games.push(PgnGame {
+            tags,
+            game: game.clone(),
+            result: game.result().to_pgn().to_string(),
+        });
+        self.game_index = self.games.len() - 1;
+        self.ply = game.moves().len();
+    }
+
+    pub fn has_games(&self) -> bool {
+        !self.games.is_empty()
+    }
+
+    pub fn current(&self) -> &PgnGame {
+        &self.games[self.game_index]
+    }
+
+    /// The current game as it stood after `ply` half-moves.
+    pub fn game(&self) -> Game {
+        let pgn_game = self.current();
+        let mut game = Game::new(pgn_game.game.starting_position().clone());
+        for mv in &pgn_game.game.moves()[..self.ply] {
+            game.play(*mv);
+        }
+        game
+    }
+
+    /// A line for the status text, e.g. `Game 1 of 3: Fischer - Spassky, ply 12 of 85`.
+    pub fn description(&self) -> String {
+        let pgn_game = self.current();
+        let total_plies = pgn_game.game.moves().len();
+        let mut description = format!(
+            "Game {} of {}: {}, ply {} of {}",
+            self.game_index + 1,
+            self.games.len(),
+            pgn_game.title(),
+            self.ply,
+            total_plies
+        );
+        if self.ply == total_plies {
+            description.push_str(&format!(" ({})", pgn_game.result));
+        }
+        description
+    }
+}
+
+/// Also lets the status text know it's showing a review.
+fn start_review(replay: Res<Replay>, mut game: ResMut<Game>) {
+    *game = replay.game();
+}
+
+/// Left and Right step through the moves, Home and End jump to the start and end of the game,
+/// Page Up and Page Down switch between games in the file. Escape goes back to the main menu.
+fn replay_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<AppState>>,
+    mut replay: ResMut<Replay>,
+    mut game: ResMut<Game>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        change_state(&mut state, AppState::MainMenu);
+        return;
+    }
+
+    let total_plies = replay.current().game.moves().len();
+    let (game_index, ply) = if keyboard_input.just_pressed(KeyCode::Right) {
+        (replay.game_index, (replay.ply + 1).min(total_plies))
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        (replay.game_index, replay.ply.saturating_sub(1))
+    } else if keyboard_input.just_pressed(KeyCode::Home) {
+        (replay.game_index, 0)
+    } else if keyboard_input.just_pressed(KeyCode::End) {
+        (replay.game_index, total_plies)
+    } else if keyboard_input.just_pressed(KeyCode::PageDown)
+        && replay.game_index + 1 < replay.games.len()
+    {
+        (replay.game_index + 1, 0)
+    } else if keyboard_input.just_pressed(KeyCode::PageUp) && replay.game_index > 0 {
+        (replay.game_index - 1, 0)
+    } else {
+        return;
+    };
+
+    if (game_index, ply) != (replay.game_index, replay.ply) {
+        replay.game_index = game_index;
+        replay.ply = ply;
+        *game = replay.game();
+    }
+}
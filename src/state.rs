@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+
+/// Which screen the app is on. Systems that take input only run in the states they belong to,
+/// and the board and pieces are only around outside of the main menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    MainMenu,
+    Playing,
+    /// The game has ended and the game over screen is up.
+    GameOver,
+    /// Stepping through finished games or games loaded from a PGN file.
+    Review,
+}
+
+/// Stage the per-state systems run in. It comes before the update stage so the board, pieces
+/// and text pick up the moves played in it within the same frame.
+pub const APP_STATE_STAGE: &str = "app_state";
+
+/// Queues going to the `next` state, returning whether it was queued. It isn't if the app is
+/// already there or another change is queued for this frame, e.g. after a click and a key press
+/// in the same frame, in which case the first change wins.
+pub fn change_state(state: &mut State<AppState>, next: AppState) -> bool {
+    state.set_next(next).is_ok()
+}